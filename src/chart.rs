@@ -0,0 +1,250 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+use charming::{
+        Chart, HtmlRenderer, ImageRenderer, ImageFormat,
+        component::{Axis, Legend, Title,},
+        element::{AxisType, ItemStyle, Label, LabelPosition},
+        series::{Bar, Line, Pie},
+        theme::Theme,
+};
+
+/// File format charts are written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChartFormat {
+        #[default]
+        Png,
+        Svg,
+        /// Standalone page that draws the chart with ECharts in the browser,
+        /// with tooltips and a clickable legend.
+        Html,
+}
+
+impl ChartFormat {
+        pub fn extension(self) -> &'static str {
+                match self {
+                        ChartFormat::Png => "png",
+                        ChartFormat::Svg => "svg",
+                        ChartFormat::Html => "html",
+                }
+        }
+
+        /// The format a file name's extension asks for, if any.
+        pub fn from_path(path: &Path) -> Option<Self> {
+                let extension = path.extension()?.to_str()?.to_lowercase();
+                [ChartFormat::Png, ChartFormat::Svg, ChartFormat::Html].into_iter()
+                        .find(|format| format.extension() == extension)
+        }
+}
+
+/// Where and how the charts of one run are written.
+///
+/// Every chart is named after `title`, e.g. the repository, plus its own
+/// name such as `organizations`; the same goes for file names and `stem`.
+#[derive(Debug, Clone)]
+pub struct ChartOptions {
+        pub title: String,
+        /// File name of the main chart, without extension.
+        pub stem: String,
+        pub dir: PathBuf,
+        pub format: ChartFormat,
+        /// Overrides the chart type's default width.
+        pub width: Option<u32>,
+        /// Overrides the chart type's default height.
+        pub height: Option<u32>,
+        pub theme: Theme,
+}
+
+impl ChartOptions {
+        /// PNG files named after `title` in the current directory.
+        pub fn new(title: &str) -> Self {
+                ChartOptions {
+                        title: title.to_string(),
+                        stem: title.to_string(),
+                        dir: PathBuf::from("."),
+                        format: ChartFormat::Png,
+                        width: None,
+                        height: None,
+                        theme: Theme::Shine,
+                }
+        }
+
+        /// Renders `chart` once, in the configured format, to the file for
+        /// the chart called `name`.
+        fn save(&self, chart: &Chart, name: &str, width: u32, height: u32) -> Result<()> {
+                let width = self.width.unwrap_or(width);
+                let height = self.height.unwrap_or(height);
+                let file_name = file_name(&with_name(&self.stem, name));
+                let path = self.dir.join(format!("{}.{}", file_name, self.format.extension()));
+                let theme = self.theme.clone();
+                match self.format {
+                        ChartFormat::Png => ImageRenderer::new(width, height).theme(theme)
+                                .save_format(ImageFormat::Png, chart, &path),
+                        ChartFormat::Svg => ImageRenderer::new(width, height).theme(theme)
+                                .save(chart, &path),
+                        ChartFormat::Html => HtmlRenderer::new(with_name(&self.title, name), width.into(), height.into())
+                                .theme(theme)
+                                .save(chart, &path),
+                }
+                .with_context(|| format!("Failed to write chart {:?}", path))
+        }
+}
+
+/// `base` for the main chart (empty `name`), `base name` otherwise.
+fn with_name(base: &str, name: &str) -> String {
+        if name.is_empty() {
+                base.to_string()
+        } else {
+                format!("{} {}", base, name)
+        }
+}
+
+/// `name` with characters that are path separators or invalid on common
+/// filesystems replaced, so people and subsystem names such as
+/// `ARM/Foo` stay a single file in the output directory.
+fn file_name(name: &str) -> String {
+        name.chars()
+                .map(|c| match c {
+                        '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+                        c if c.is_control() => '_',
+                        c => c,
+                })
+                .collect()
+}
+
+// --- CHARMING (ECharts) GENERATOR ---
+/// Pie chart called `name`; an empty name is the run's main chart.
+pub fn generate_pie_chart(options: &ChartOptions, name: &str, date: &str, data: Vec<(String, usize)>) -> Result<()> {
+
+        let title = with_name(&options.title, name);
+        // FIX 1: Swap the order. Charming expects (Value, Label), not (Label, Value)
+        let pie_data: Vec<(i64, String)> = data.into_iter()
+                .map(|(label, value)| (value as i64, label))
+                .collect();
+
+        // 1. Configure the Title
+        //let title = Title::new()
+        //.text(title)
+        //.subtext(date)
+        //.left("center")
+        //.text_style(TextStyle::new().font_size(25));
+
+        // SERIES 1: The Percentages (Inside the colored box)
+        let inner_series = Pie::new()
+                .name(&title)
+                .radius("70%")
+                .data(pie_data.clone()) // Clone data for the first series
+                .item_style(ItemStyle::new().border_radius(10).border_color("#fff").border_width(2))
+                .label(
+                        Label::new()
+                        .show(true)
+                        .position(LabelPosition::Inside)
+                        .formatter("{d}%") // Show only percentage
+                        .color("#fff")
+                        .font_weight("bold")
+                );
+
+        // SERIES 2: The Labels (Outside with pointer lines)
+        let outer_series = Pie::new()
+                .name(&title)
+                .radius("70%") // Same radius so it overlaps perfectly
+                .data(pie_data)
+                .item_style(ItemStyle::new().border_radius(10).border_color("#fff").border_width(2))
+                .label(
+                        Label::new()
+                        .show(true)
+                        .position(LabelPosition::Outside)
+                        .formatter("{b}") // Show only the Name (e.g., "Authored")
+                        .color("#000")
+                );
+
+        let chart = Chart::new()
+                .legend(Legend::new().top("bottom"))
+                .title(
+                        Title::new()
+                        .text(&title)
+                        .subtext(date)
+                        .left("center"),
+                )
+                .series(inner_series) // Add Series 1
+                .series(outer_series); // Add Series 2
+
+        // Chart dimension 800x800 unless overridden.
+        options.save(&chart, name, 800, 800)
+}
+
+/// Grouped bar chart: one group per category on the x axis and one bar per
+/// series inside each group, e.g. people vs. Authored/Reviewed/...
+pub fn generate_bar_chart(options: &ChartOptions, name: &str, date: &str, categories: Vec<String>,
+        series: Vec<(String, Vec<usize>)>) -> Result<()> {
+
+        let title = with_name(&options.title, name);
+
+        let mut chart = Chart::new()
+                .legend(Legend::new().top("bottom"))
+                .title(
+                        Title::new()
+                        .text(&title)
+                        .subtext(date)
+                        .left("center"),
+                )
+                .x_axis(Axis::new().type_(AxisType::Category).data(categories))
+                .y_axis(Axis::new().type_(AxisType::Value));
+
+        for (name, values) in series {
+                let values: Vec<i64> = values.into_iter().map(|v| v as i64).collect();
+                chart = chart.series(Bar::new().name(name).data(values));
+        }
+
+        options.save(&chart, name, 1000, 800)
+}
+
+/// How [`generate_trend_chart`] draws a time series.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrendStyle {
+        /// One bar per bucket with the categories stacked on top of each other.
+        StackedBar,
+        /// One line per category.
+        Line,
+}
+
+/// Time series chart: buckets on the x axis, oldest first, and one series
+/// per category, e.g. Authored/Reviewed/... per month.
+pub fn generate_trend_chart(options: &ChartOptions, name: &str, date: &str, buckets: Vec<String>,
+        series: Vec<(String, Vec<usize>)>, style: TrendStyle) -> Result<()> {
+
+        let title = with_name(&options.title, name);
+
+        let mut chart = Chart::new()
+                .legend(Legend::new().top("bottom"))
+                .title(
+                        Title::new()
+                        .text(&title)
+                        .subtext(date)
+                        .left("center"),
+                )
+                .x_axis(Axis::new().type_(AxisType::Category).data(buckets))
+                .y_axis(Axis::new().type_(AxisType::Value));
+
+        for (name, values) in series {
+                let values: Vec<i64> = values.into_iter().map(|v| v as i64).collect();
+                chart = match style {
+                        TrendStyle::StackedBar => chart.series(Bar::new().name(name).stack("activity").data(values)),
+                        TrendStyle::Line => chart.series(Line::new().name(name).data(values)),
+                };
+        }
+
+        options.save(&chart, name, 1000, 800)
+}
+
+#[cfg(test)]
+mod tests {
+        use super::*;
+
+        #[test]
+        fn file_names_keep_names_in_one_component() {
+                assert_eq!(file_name("Contributions ARM/Foo subsystems"), "Contributions ARM_Foo subsystems");
+                assert_eq!(file_name("a\\b: c?"), "a_b_ c_");
+                assert_eq!(file_name("Bob Smith"), "Bob Smith");
+        }
+}
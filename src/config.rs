@@ -0,0 +1,134 @@
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Looked up in the current directory when a report is selected without
+/// naming a config file.
+pub const DEFAULT_CONFIG: &str = "git_stats.toml";
+
+/// One `[reports.<name>]` table. Unset options keep their defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ReportDefinition {
+        pub paths: Vec<PathBuf>,
+        pub emails: Vec<String>,
+        pub orgs: Vec<String>,
+        pub orgs_file: Option<PathBuf>,
+        pub partial: bool,
+        pub mailmap: Option<PathBuf>,
+        pub no_mailmap: bool,
+        pub since: Option<String>,
+        pub until: Option<String>,
+        pub date: Option<String>,
+        pub ranges: Vec<String>,
+        pub upstream_ranges: Vec<String>,
+        pub branches: bool,
+        pub remotes: bool,
+        pub tags: bool,
+        pub globs: Vec<String>,
+        pub merges: Option<String>,
+        pub first_parent: bool,
+        pub require_signoff: bool,
+        pub trailers: Vec<String>,
+        pub trailers_file: Option<PathBuf>,
+        pub path_filters: Vec<String>,
+        pub maintainers: Option<MaintainersSource>,
+        pub diffstat: bool,
+        pub exclude_paths: Vec<String>,
+        pub bucket: Option<String>,
+        pub trend_chart: Option<String>,
+        pub people_chart: Option<String>,
+        pub format: Option<String>,
+        pub include_commits: bool,
+        pub verbose: bool,
+        pub mail_archives: Vec<PathBuf>,
+        pub public_inboxes: Vec<PathBuf>,
+        pub chart_output: Option<PathBuf>,
+        pub chart_format: Option<String>,
+        pub chart_width: Option<u32>,
+        pub chart_height: Option<u32>,
+        pub chart_theme: Option<String>,
+        pub no_chart: bool,
+}
+
+/// The `maintainers` key: `true` reads the repository's own MAINTAINERS at
+/// HEAD like a bare `--maintainers`, a string names the file to use.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum MaintainersSource {
+        Repository(bool),
+        File(PathBuf),
+}
+
+/// Named report definitions from a TOML file, so recurring reports do
+/// not need their long lists of emails, repositories and trailers on
+/// the command line:
+///
+/// ```toml
+/// [reports.arm-monthly]
+/// paths = ["~/src/linux", "~/src/u-boot"]
+/// orgs-file = "orgs.txt"
+/// orgs = ["Arm"]
+/// since = "2024-01-01"
+/// until = "2024-01-31"
+/// trailers = ["Suggested-by"]
+/// bucket = "week"
+/// format = "json"
+/// ```
+///
+/// Keys are the command line's long options; options that may be repeated
+/// take a list under their plural name (`paths`, `emails`, `orgs`,
+/// `ranges`, `upstream-ranges`, `globs`, `trailers`, `path-filters`,
+/// `exclude-paths`, `mail-archives`, `public-inboxes`).
+/// Values stay strings here and are validated like the command line's.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+        #[serde(default)]
+        pub reports: BTreeMap<String, ReportDefinition>,
+}
+
+impl ConfigFile {
+        /// Loads `path`. Relative paths inside it are taken relative to the
+        /// file's own directory, and a leading `~/` to the home directory.
+        pub fn load(path: &Path) -> Result<Self> {
+                let content = fs::read_to_string(path)
+                        .with_context(|| format!("Failed to read config file {:?}", path))?;
+                let mut config: ConfigFile = toml::from_str(&content)
+                        .with_context(|| format!("Failed to parse config file {:?}", path))?;
+                let base = path.parent().unwrap_or(Path::new(""));
+                for report in config.reports.values_mut() {
+                        for file in report.paths.iter_mut()
+                                .chain(&mut report.orgs_file)
+                                .chain(&mut report.mailmap)
+                                .chain(&mut report.trailers_file)
+                                .chain(&mut report.mail_archives)
+                                .chain(&mut report.public_inboxes)
+                                .chain(match &mut report.maintainers {
+                                        Some(MaintainersSource::File(file)) => Some(file),
+                                        _ => None,
+                                })
+                                .chain(&mut report.chart_output) {
+                                *file = resolve(base, file);
+                        }
+                }
+                Ok(config)
+        }
+
+        pub fn report(&self, name: &str) -> Result<&ReportDefinition> {
+                self.reports.get(name).ok_or_else(|| {
+                        let names: Vec<&str> = self.reports.keys().map(String::as_str).collect();
+                        anyhow!("No report named {:?}; defined reports: {}", name, names.join(", "))
+                })
+        }
+}
+
+fn resolve(base: &Path, path: &Path) -> PathBuf {
+        if let Ok(rest) = path.strip_prefix("~")
+                && let Some(home) = std::env::var_os("HOME") {
+                return PathBuf::from(home).join(rest);
+        }
+        base.join(path)
+}
@@ -0,0 +1,66 @@
+use crate::diffstat::DiffStat;
+use crate::report::ContributionReport;
+
+const HEADER: [&str; 11] = ["hash", "date", "author", "summary", "roles", "trailers",
+        "files_changed", "insertions", "deletions", "subsystems", "repository"];
+
+/// One row per matched commit: what `--verbose` prints, plus the roles the
+/// targets played and the trailer lines that matched. Multiple roles or
+/// trailers in one cell are separated by `; `. The diffstat and subsystem
+/// columns are empty unless those were computed.
+pub fn to_csv(report: &ContributionReport) -> String {
+        let mut out = HEADER.join(",");
+        out.push('\n');
+        for commit in &report.commits {
+                let roles: Vec<String> = commit.roles.iter().map(|role| role.name(&report.trailers)).collect();
+                let trailers: Vec<String> = commit.trailers.iter()
+                        .map(|t| format!("{}: {}", t.key, t.value))
+                        .collect();
+                let author = if commit.author_email.is_empty() {
+                        commit.author.clone()
+                } else {
+                        format!("{} <{}>", commit.author, commit.author_email)
+                };
+                let stat = |f: fn(&DiffStat) -> usize| commit.diffstat.as_ref().map(f)
+                        .map_or(String::new(), |n| n.to_string());
+                let row = [
+                        commit.id.to_string(),
+                        commit.date.format("%Y-%m-%d").to_string(),
+                        author,
+                        commit.summary.clone(),
+                        roles.join("; "),
+                        trailers.join("; "),
+                        stat(|s| s.files_changed),
+                        stat(|s| s.insertions),
+                        stat(|s| s.deletions),
+                        commit.subsystems.join("; "),
+                        commit.repository.clone(),
+                ];
+                let fields: Vec<String> = row.iter().map(|field| escape(field)).collect();
+                out.push_str(&fields.join(","));
+                out.push('\n');
+        }
+        out
+}
+
+/// Quotes a field per RFC 4180 when it contains a delimiter, quote or newline.
+fn escape(field: &str) -> String {
+        if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+                field.to_string()
+        }
+}
+
+#[cfg(test)]
+mod tests {
+        use super::*;
+
+        #[test]
+        fn escapes_only_fields_that_need_it() {
+                assert_eq!(escape("plain text"), "plain text");
+                assert_eq!(escape("a, b"), "\"a, b\"");
+                assert_eq!(escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+                assert_eq!(escape("two\nlines"), "\"two\nlines\"");
+        }
+}
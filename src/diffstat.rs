@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use git2::{Commit, Diff, DiffOptions, Oid, Patch, Repository};
+use glob::Pattern;
+use std::ops::AddAssign;
+
+/// Size of a change: files touched and lines added/removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffStat {
+        pub files_changed: usize,
+        pub insertions: usize,
+        pub deletions: usize,
+}
+
+impl AddAssign for DiffStat {
+        fn add_assign(&mut self, other: DiffStat) {
+                self.files_changed += other.files_changed;
+                self.insertions += other.insertions;
+                self.deletions += other.deletions;
+        }
+}
+
+/// Parses `--exclude-path` globs such as `vendor/**` or `*.generated.c`.
+pub fn parse_excludes(globs: &[String]) -> Result<Vec<Pattern>> {
+        globs.iter()
+                .map(|glob| Pattern::new(glob).with_context(|| format!("Invalid path glob {:?}", glob)))
+                .collect()
+}
+
+/// Diff of `commit` against its first parent, or against the empty tree
+/// for a root commit.
+fn first_parent_diff<'r>(repo: &'r Repository, commit: &Commit, opts: Option<&mut DiffOptions>) -> Result<Diff<'r>> {
+        let tree = commit.tree().context("Failed to read commit tree")?;
+        let parent_tree = match commit.parents().next() {
+                Some(parent) => Some(parent.tree().context("Failed to read parent tree")?),
+                None => None,
+        };
+        repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), opts)
+                .context("Failed to diff commit against its parent")
+}
+
+/// Whether `commit` changes anything matched by git-style `pathspecs`
+/// (`drivers/firmware/`, `lib/efi_loader/*.c`).
+pub(crate) fn touches_paths(repo: &Repository, commit: &Commit, pathspecs: &[String]) -> Result<bool> {
+        let mut opts = DiffOptions::new();
+        for pathspec in pathspecs {
+                opts.pathspec(pathspec);
+        }
+        let diff = first_parent_diff(repo, commit, Some(&mut opts))?;
+        Ok(diff.deltas().len() > 0)
+}
+
+/// Patch-id of `commit` against its first parent: the same change gives
+/// the same id after a rebase or a cherry-pick. It equals what
+/// `git patch-id --stable` prints for a diff made with `--no-renames
+/// --no-indent-heuristic`; git's default diff can split hunks differently
+/// and give another id. `None` for a commit that changes nothing.
+pub(crate) fn patch_id(repo: &Repository, commit: &Commit) -> Result<Option<Oid>> {
+        let diff = first_parent_diff(repo, commit, None)?;
+        if diff.deltas().len() == 0 {
+                return Ok(None);
+        }
+        Ok(Some(diff.patchid(None).context("Failed to compute patch-id")?))
+}
+
+/// Paths `commit` adds, modifies or deletes relative to its first parent.
+pub(crate) fn changed_paths(repo: &Repository, commit: &Commit) -> Result<Vec<String>> {
+        let diff = first_parent_diff(repo, commit, None)?;
+        Ok(diff.deltas()
+                .filter_map(|delta| delta.new_file().path().or(delta.old_file().path())
+                        .map(|path| path.to_string_lossy().into_owned()))
+                .collect())
+}
+
+/// Diffstat of `commit` against its first parent (or the empty tree for a
+/// root commit), ignoring files whose path matches one of `excludes`.
+/// Renames count as one changed file with only the lines that changed.
+pub(crate) fn commit_diffstat(repo: &Repository, commit: &Commit, excludes: &[Pattern]) -> Result<DiffStat> {
+        let mut diff = first_parent_diff(repo, commit, None)?;
+        diff.find_similar(None).context("Failed to detect renames")?;
+
+        if excludes.is_empty() {
+                let stats = diff.stats().context("Failed to compute diffstat")?;
+                return Ok(DiffStat {
+                        files_changed: stats.files_changed(),
+                        insertions: stats.insertions(),
+                        deletions: stats.deletions(),
+                });
+        }
+
+        let mut stat = DiffStat::default();
+        for (index, delta) in diff.deltas().enumerate() {
+                let path = delta.new_file().path().or(delta.old_file().path());
+                if path.is_some_and(|path| excludes.iter().any(|pattern| pattern.matches_path(path))) {
+                        continue;
+                }
+                stat.files_changed += 1;
+                if let Some(patch) = Patch::from_diff(&diff, index).context("Failed to load patch")? {
+                        let (_, insertions, deletions) = patch.line_stats()?;
+                        stat.insertions += insertions;
+                        stat.deletions += deletions;
+                }
+        }
+        Ok(stat)
+}
@@ -0,0 +1,187 @@
+use anyhow::{Context, Result};
+use git2::{Mailmap, Repository, Signature};
+use std::fs;
+use std::path::Path;
+
+use crate::org::Organizations;
+
+/// A `Name <email>` identity as found in author fields and trailer values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+        pub name: Option<String>,
+        pub email: String,
+}
+
+impl Identity {
+        /// Extracts the identity from a trailer value such as
+        /// `Jane Doe <jane@example.org>`, `<jane@example.org>` or a bare
+        /// `jane@example.org`. Trailing comments after the address are ignored.
+        pub fn parse(value: &str) -> Option<Identity> {
+                let value = value.trim();
+                if let Some(open) = value.find('<') {
+                        let close = open + value[open..].find('>')?;
+                        let email = value[open + 1..close].trim();
+                        if !is_email(email) {
+                                return None;
+                        }
+                        let name = value[..open].trim().trim_matches('"').trim();
+                        return Some(Identity {
+                                name: (!name.is_empty()).then(|| name.to_string()),
+                                email: email.to_string(),
+                        });
+                }
+
+                let email = value.split_whitespace().next()?;
+                is_email(email).then(|| Identity { name: None, email: email.to_string() })
+        }
+}
+
+fn is_email(s: &str) -> bool {
+        match s.split_once('@') {
+                Some((local, domain)) => !local.is_empty() && !domain.is_empty()
+                        && !s.contains(char::is_whitespace),
+                None => false,
+        }
+}
+
+/// Decides whether an email address belongs to one of the targets.
+///
+/// Exact mode compares whole addresses; partial mode accepts any address
+/// containing a target (e.g. `@linaro.org`). Both are case-insensitive.
+/// Addresses of the target organizations always match.
+#[derive(Debug, Clone)]
+pub struct EmailMatcher {
+        targets: Vec<String>,
+        partial: bool,
+        organizations: Organizations,
+        target_orgs: Vec<String>,
+}
+
+impl EmailMatcher {
+        pub fn new(targets: &[String], partial: bool) -> Self {
+                EmailMatcher {
+                        targets: targets.iter().map(|e| e.to_lowercase()).collect(),
+                        partial,
+                        organizations: Organizations::default(),
+                        target_orgs: Vec::new(),
+                }
+        }
+
+        /// Also match every address `organizations` places in one of `names`.
+        pub fn with_orgs(mut self, organizations: &Organizations, names: &[String]) -> Self {
+                self.organizations = organizations.clone();
+                self.target_orgs = names.to_vec();
+                self
+        }
+
+        /// This matcher plus the mailmapped address of each exact target, so
+        /// an old address given as a target still matches once the mailmap
+        /// rewrites it to the canonical one. Partial targets are kept as is.
+        pub fn resolved(&self, resolver: &IdentityResolver) -> Self {
+                let mut matcher = self.clone();
+                if !self.partial {
+                        for target in &self.targets {
+                                let identity = resolver.resolve(Identity { name: None, email: target.clone() });
+                                let email = identity.email.to_lowercase();
+                                if !matcher.targets.contains(&email) {
+                                        matcher.targets.push(email);
+                                }
+                        }
+                }
+                matcher
+        }
+
+        pub fn matches(&self, email: &str) -> bool {
+                if !self.target_orgs.is_empty()
+                        && let Some(org) = self.organizations.lookup(email)
+                        && self.target_orgs.iter().any(|name| name.eq_ignore_ascii_case(org)) {
+                        return true;
+                }
+                let email = email.to_lowercase();
+                if self.partial {
+                        self.targets.iter().any(|target| email.contains(target.as_str()))
+                } else {
+                        self.targets.contains(&email)
+                }
+        }
+}
+
+/// Resolves identities through the repository's `.mailmap` and an optional
+/// extra mailmap file, so one person's old and new addresses are counted
+/// as the same identity. The extra file is applied after the repository's.
+pub struct IdentityResolver {
+        mailmaps: Vec<Mailmap>,
+}
+
+impl IdentityResolver {
+        /// A resolver that returns every identity unchanged.
+        pub fn disabled() -> Self {
+                IdentityResolver { mailmaps: Vec::new() }
+        }
+
+        pub fn new(repo: &Repository, extra: Option<&Path>) -> Result<Self> {
+                let mut mailmaps = vec![repo.mailmap().context("Failed to load repository mailmap")?];
+                if let Some(path) = extra {
+                        let content = fs::read_to_string(path)
+                                .with_context(|| format!("Failed to read mailmap {:?}", path))?;
+                        mailmaps.push(Mailmap::from_buffer(&content)
+                                .with_context(|| format!("Failed to parse mailmap {:?}", path))?);
+                }
+                Ok(IdentityResolver { mailmaps })
+        }
+
+        pub fn resolve(&self, identity: Identity) -> Identity {
+                let name = identity.name.as_deref().unwrap_or(&identity.email);
+                match Signature::now(name, &identity.email) {
+                        Ok(sig) => self.resolve_signature(&sig).unwrap_or(identity),
+                        Err(_) => identity,
+                }
+        }
+
+        /// The mailmapped identity of `sig`, or `None` if it has no email.
+        pub fn resolve_signature(&self, sig: &Signature) -> Option<Identity> {
+                let mut resolved = None;
+                for mailmap in &self.mailmaps {
+                        let next = mailmap.resolve_signature(resolved.as_ref().unwrap_or(sig)).ok()?;
+                        resolved = Some(next);
+                }
+                let sig = resolved.as_ref().unwrap_or(sig);
+                Some(Identity {
+                        name: sig.name().map(str::to_string).filter(|n| !n.is_empty()),
+                        email: sig.email()?.to_string(),
+                })
+        }
+}
+
+#[cfg(test)]
+mod tests {
+        use super::*;
+
+        fn identity(name: Option<&str>, email: &str) -> Option<Identity> {
+                Some(Identity { name: name.map(str::to_string), email: email.to_string() })
+        }
+
+        #[test]
+        fn parses_identity_forms() {
+                assert_eq!(Identity::parse("Jane Doe <jane@x.org>"), identity(Some("Jane Doe"), "jane@x.org"));
+                assert_eq!(Identity::parse("\"Doe, Jane\" <jane@x.org> # v5.10+"), identity(Some("Doe, Jane"), "jane@x.org"));
+                assert_eq!(Identity::parse("<jane@x.org>"), identity(None, "jane@x.org"));
+                assert_eq!(Identity::parse("jane@x.org (maintainer)"), identity(None, "jane@x.org"));
+        }
+
+        #[test]
+        fn rejects_values_without_an_email() {
+                assert_eq!(Identity::parse("syzbot"), None);
+                assert_eq!(Identity::parse("Jane <jane>"), None);
+                assert_eq!(Identity::parse("Jane <jane@x.org"), None);
+        }
+
+        #[test]
+        fn exact_matching_does_not_match_substrings() {
+                let matcher = EmailMatcher::new(&["bob@linaro.org".to_string()], false);
+                assert!(matcher.matches("Bob@Linaro.org"));
+                assert!(!matcher.matches("jimbob@linaro.org"));
+                let partial = EmailMatcher::new(&["@linaro.org".to_string()], true);
+                assert!(partial.matches("jimbob@linaro.org"));
+        }
+}
@@ -0,0 +1,301 @@
+use anyhow::Result;
+use chrono::Duration;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::diffstat::DiffStat;
+use crate::report::{ActivityRow, ContributionReport, MatchedCommit};
+use crate::scan::{MergeMode, ScanConfig};
+use crate::timeline::Bucket;
+use crate::trailers::TrailerMatch;
+
+/// Bumped whenever a field is renamed or removed; new fields may be added
+/// without a bump.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+        schema_version: u32,
+        scan: JsonScan<'a>,
+        total_scanned: usize,
+        authored: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        merged: Option<usize>,
+        committed: usize,
+        trailers: Vec<JsonTrailer<'a>>,
+        /// Commits the targets took part in, each counted once.
+        involved_commits: usize,
+        /// `involved_commits` split by each commit's first role.
+        involvement: Vec<JsonInvolvement>,
+        unparsed_trailers: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        diffstats: Option<Vec<JsonDiffStat>>,
+        people: Vec<JsonRow<'a>>,
+        repositories: Vec<JsonRow<'a>>,
+        organizations: Vec<JsonRow<'a>>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        subsystems: Vec<JsonRow<'a>>,
+        /// One row per time bucket, named like `2024-Q1`, oldest first.
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        timeline: Vec<JsonRow<'a>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        mailing_list: Option<JsonMailingList<'a>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        upstream: Option<JsonUpstream<'a>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        commits: Option<Vec<JsonCommit<'a>>>,
+}
+
+#[derive(Serialize)]
+struct JsonScan<'a> {
+        paths: Vec<String>,
+        emails: &'a [String],
+        orgs: &'a [String],
+        partial: bool,
+        merges: &'static str,
+        first_parent: bool,
+        require_signoff: bool,
+        since: Option<String>,
+        until: Option<String>,
+        revisions: &'a [String],
+        ref_globs: &'a [String],
+        path_filters: &'a [String],
+        #[serde(skip_serializing_if = "Option::is_none")]
+        bucket: Option<&'static str>,
+        range: String,
+}
+
+#[derive(Serialize)]
+struct JsonTrailer<'a> {
+        key: &'a str,
+        label: &'a str,
+        count: usize,
+}
+
+#[derive(Serialize)]
+struct JsonMailingList<'a> {
+        messages: usize,
+        patches: usize,
+        replies: usize,
+        /// Replies giving each identity trailer kind, by key.
+        tags: BTreeMap<&'a str, usize>,
+        comments: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reply_list: Option<Vec<JsonReply<'a>>>,
+}
+
+#[derive(Serialize)]
+struct JsonReply<'a> {
+        message_id: &'a str,
+        date: Option<String>,
+        from: &'a str,
+        subject: &'a str,
+        tags: &'a [String],
+        comments: bool,
+}
+
+#[derive(Serialize)]
+struct JsonUpstream<'a> {
+        landed: usize,
+        pending: usize,
+        patches: Vec<JsonUpstreamPatch<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonUpstreamPatch<'a> {
+        id: String,
+        repository: &'a str,
+        date: String,
+        author_email: &'a str,
+        summary: &'a str,
+        /// The upstream commit; absent while pending.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        upstream_id: Option<String>,
+        /// `patch-id` or `subject`.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        matched_by: Option<&'static str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        release: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct JsonInvolvement {
+        label: String,
+        commits: usize,
+}
+
+#[derive(Serialize)]
+struct JsonDiffStat {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        label: Option<String>,
+        files_changed: usize,
+        insertions: usize,
+        deletions: usize,
+}
+
+impl JsonDiffStat {
+        fn new(label: Option<String>, stat: DiffStat) -> Self {
+                JsonDiffStat {
+                        label,
+                        files_changed: stat.files_changed,
+                        insertions: stat.insertions,
+                        deletions: stat.deletions,
+                }
+        }
+}
+
+#[derive(Serialize)]
+struct JsonRow<'a> {
+        name: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        email: Option<&'a str>,
+        authored: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        merged: Option<usize>,
+        committed: usize,
+        /// Keyed by trailer key, e.g. `Reviewed-by`.
+        trailers: BTreeMap<&'a str, usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        diffstat: Option<JsonDiffStat>,
+        /// Matched commits per subsystem, for people rows.
+        #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+        subsystems: BTreeMap<&'a str, usize>,
+}
+
+#[derive(Serialize)]
+struct JsonCommit<'a> {
+        id: String,
+        repository: &'a str,
+        date: String,
+        author: &'a str,
+        summary: &'a str,
+        roles: Vec<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        diffstat: Option<JsonDiffStat>,
+        #[serde(skip_serializing_if = "<[_]>::is_empty")]
+        subsystems: &'a [String],
+}
+
+/// Renders `report` as pretty-printed JSON following schema
+/// [`SCHEMA_VERSION`]. Matched commits are only listed when
+/// `include_commits` is set.
+pub fn to_json(config: &ScanConfig, report: &ContributionReport, include_commits: bool) -> Result<String> {
+        let json = JsonReport {
+                schema_version: SCHEMA_VERSION,
+                scan: JsonScan {
+                        paths: config.paths.iter().map(|path| path.display().to_string()).collect(),
+                        emails: &config.emails,
+                        orgs: &config.orgs,
+                        partial: config.partial,
+                        merges: match config.merges {
+                                MergeMode::Include => "include",
+                                MergeMode::Exclude => "exclude",
+                                MergeMode::Separate => "separate",
+                        },
+                        first_parent: config.first_parent,
+                        require_signoff: config.require_signoff,
+                        since: config.since.map(|d| d.format("%Y-%m-%d").to_string()),
+                        until: config.until.map(|d| (d - Duration::seconds(1)).format("%Y-%m-%d").to_string()),
+                        revisions: &config.revisions,
+                        ref_globs: &config.ref_globs,
+                        path_filters: &config.path_filters,
+                        bucket: config.bucket.map(|bucket| match bucket {
+                                Bucket::Week => "week",
+                                Bucket::Month => "month",
+                                Bucket::Quarter => "quarter",
+                                Bucket::Year => "year",
+                        }),
+                        range: config.range_label(),
+                },
+                total_scanned: report.total_scanned,
+                authored: report.authored,
+                merged: report.merged,
+                committed: report.committed,
+                trailers: report.trailers.iter()
+                        .map(|t| JsonTrailer { key: &t.kind.key, label: &t.kind.label, count: t.count })
+                        .collect(),
+                involved_commits: report.involved(),
+                involvement: report.involvement().into_iter()
+                        .map(|(label, commits)| JsonInvolvement { label, commits })
+                        .collect(),
+                unparsed_trailers: report.unparsed_trailers.len(),
+                diffstats: config.diffstat.then(|| report.category_diffstats().into_iter()
+                        .map(|(label, stat)| JsonDiffStat::new(Some(label), stat))
+                        .collect()),
+                people: report.people.iter().map(|row| json_row(report, row, config.diffstat)).collect(),
+                repositories: report.repositories.iter().map(|row| json_row(report, row, config.diffstat)).collect(),
+                organizations: report.organizations.iter().map(|row| json_row(report, row, false)).collect(),
+                subsystems: report.subsystems.iter().map(|row| json_row(report, row, config.diffstat)).collect(),
+                timeline: report.timeline.iter().map(|row| json_row(report, row, config.diffstat)).collect(),
+                mailing_list: report.mailing_list.as_ref().map(|list| JsonMailingList {
+                        messages: list.messages,
+                        patches: list.patches,
+                        replies: list.replies.len(),
+                        tags: report.trailers.iter().zip(&list.tags)
+                                .filter(|(t, _)| t.kind.mode == TrailerMatch::Identity)
+                                .map(|(t, count)| (t.kind.key.as_str(), *count))
+                                .collect(),
+                        comments: list.comments,
+                        reply_list: include_commits.then(|| list.replies.iter()
+                                .map(|reply| JsonReply {
+                                        message_id: &reply.message_id,
+                                        date: reply.date.map(|d| d.to_rfc3339()),
+                                        from: &reply.from.email,
+                                        subject: &reply.subject,
+                                        tags: &reply.tags,
+                                        comments: reply.comments,
+                                })
+                                .collect()),
+                }),
+                upstream: report.upstream.as_ref().map(|patches| {
+                        let (landed, pending) = report.upstream_counts();
+                        JsonUpstream {
+                                landed,
+                                pending,
+                                patches: patches.iter()
+                                        .map(|patch| JsonUpstreamPatch {
+                                                id: patch.id.to_string(),
+                                                repository: &patch.repository,
+                                                date: patch.date.to_rfc3339(),
+                                                author_email: &patch.author_email,
+                                                summary: &patch.summary,
+                                                upstream_id: patch.landed.as_ref().map(|l| l.id.to_string()),
+                                                matched_by: patch.landed.as_ref().map(|l| l.matched_by.name()),
+                                                release: patch.landed.as_ref().and_then(|l| l.release.as_deref()),
+                                        })
+                                        .collect(),
+                        }
+                }),
+                commits: include_commits.then(|| report.commits.iter().map(|commit| json_commit(report, commit)).collect()),
+        };
+        Ok(serde_json::to_string_pretty(&json)?)
+}
+
+fn json_row<'a>(report: &'a ContributionReport, row: &'a ActivityRow, diffstat: bool) -> JsonRow<'a> {
+        JsonRow {
+                name: &row.name,
+                email: row.email.as_deref(),
+                authored: row.authored,
+                merged: row.merged,
+                committed: row.committed,
+                trailers: report.trailers.iter()
+                        .zip(&row.trailers)
+                        .map(|(t, count)| (t.kind.key.as_str(), *count))
+                        .collect(),
+                diffstat: diffstat.then(|| JsonDiffStat::new(None, row.diffstat)),
+                subsystems: row.subsystems.iter().map(|(name, count)| (name.as_str(), *count)).collect(),
+        }
+}
+
+fn json_commit<'a>(report: &ContributionReport, commit: &'a MatchedCommit) -> JsonCommit<'a> {
+        JsonCommit {
+                id: commit.id.to_string(),
+                repository: &commit.repository,
+                date: commit.date.to_rfc3339(),
+                author: &commit.author,
+                summary: &commit.summary,
+                roles: commit.roles.iter().map(|role| role.name(&report.trailers)).collect(),
+                diffstat: commit.diffstat.map(|stat| JsonDiffStat::new(None, stat)),
+                subsystems: &commit.subsystems,
+        }
+}
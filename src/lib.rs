@@ -0,0 +1,32 @@
+//! Contribution statistics for git repositories.
+//!
+//! The binary is a thin wrapper around [`scan`]: build a [`ScanConfig`],
+//! walk the repository and get a [`ContributionReport`] back with the
+//! per-category counts and every commit the target identities touched.
+
+pub mod chart;
+pub mod config;
+pub mod csv;
+pub mod diffstat;
+pub mod identity;
+pub mod json;
+mod mail;
+pub mod maintainers;
+pub mod org;
+pub mod report;
+pub mod repository;
+pub mod scan;
+pub mod timeline;
+pub mod trailers;
+mod upstream;
+
+pub use config::{ConfigFile, MaintainersSource, ReportDefinition};
+pub use diffstat::DiffStat;
+pub use identity::{EmailMatcher, Identity, IdentityResolver};
+pub use maintainers::Maintainers;
+pub use org::Organizations;
+pub use report::{ActivityRow, ContributionReport, Landing, ListActivity, ListReply, MatchedCommit, Role, TrailerCount, UnparsedTrailer, UpstreamMatch, UpstreamPatch};
+pub use repository::{combined_name, find_repositories, repository_names};
+pub use scan::{DateField, MergeMode, ScanConfig, parse_date, parse_end_date, scan};
+pub use timeline::Bucket;
+pub use trailers::{Trailer, TrailerKind, TrailerMatch, parse_trailers};
@@ -0,0 +1,375 @@
+use anyhow::{Context, Result, bail};
+use chrono::DateTime;
+use git2::{Repository, Sort};
+use mailparse::{MailHeaderMap, ParsedMail, dateparse, parse_mail};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::identity::{EmailMatcher, Identity, IdentityResolver};
+use crate::report::{ListActivity, ListReply};
+use crate::scan::ScanConfig;
+use crate::trailers::{TrailerKind, TrailerMatch};
+
+/// Reads mailing-list messages and credits the targets' patches and review
+/// replies: replies from a target that give a tag such as `Reviewed-by`
+/// naming a target, or that comment inline on a quoted patch.
+///
+/// Only replies (`Re:` subjects) count, so tags carried along in a new
+/// version of a series are not mistaken for reviews. Messages are
+/// deduplicated by Message-ID across archives.
+pub(crate) struct MailScanner<'a> {
+        config: &'a ScanConfig,
+        matcher: &'a EmailMatcher,
+        resolver: &'a IdentityResolver,
+        seen: HashSet<String>,
+        pub(crate) activity: ListActivity,
+}
+
+impl<'a> MailScanner<'a> {
+        pub(crate) fn new(config: &'a ScanConfig, matcher: &'a EmailMatcher, resolver: &'a IdentityResolver) -> Self {
+                MailScanner {
+                        config,
+                        matcher,
+                        resolver,
+                        seen: HashSet::new(),
+                        activity: ListActivity {
+                                tags: vec![0; config.trailer_kinds.len()],
+                                ..Default::default()
+                        },
+                }
+        }
+
+        /// Reads an mbox file, or a maildir with `cur`/`new` subdirectories.
+        pub(crate) fn read_archive(&mut self, path: &Path) -> Result<()> {
+                if !path.is_dir() {
+                        let data = fs::read(path).with_context(|| format!("Failed to read mbox {:?}", path))?;
+                        for raw in split_mbox(&data) {
+                                self.message(&raw);
+                        }
+                        return Ok(());
+                }
+                if !path.join("cur").is_dir() && !path.join("new").is_dir() {
+                        bail!("{:?} is neither an mbox file nor a maildir", path);
+                }
+                for dir in [path.join("new"), path.join("cur")] {
+                        if !dir.is_dir() {
+                                continue;
+                        }
+                        let mut files: Vec<_> = fs::read_dir(&dir)
+                                .with_context(|| format!("Failed to read maildir {:?}", dir))?
+                                .map(|entry| entry.map(|e| e.path()))
+                                .collect::<std::io::Result<_>>()?;
+                        files.sort();
+                        for file in files.iter().filter(|f| f.is_file()) {
+                                let raw = fs::read(file).with_context(|| format!("Failed to read message {:?}", file))?;
+                                self.message(&raw);
+                        }
+                }
+                Ok(())
+        }
+
+        /// Reads a public-inbox v2 archive: every epoch repository under its
+        /// `git` directory, oldest first. `path` may also be a single epoch.
+        /// Each commit stores one message as the blob `m`; commits that
+        /// removed a message have none.
+        pub(crate) fn read_public_inbox(&mut self, path: &Path) -> Result<()> {
+                for epoch in epochs(path)? {
+                        let repo = Repository::open_bare(&epoch)
+                                .with_context(|| format!("Failed to open public-inbox epoch {:?}", epoch))?;
+                        if repo.head().is_err() {
+                                continue;
+                        }
+                        let mut revwalk = repo.revwalk().context("Failed to initialize revision walker")?;
+                        revwalk.set_sorting(Sort::TIME | Sort::REVERSE)?;
+                        revwalk.push_head()?;
+                        for oid in revwalk {
+                                let commit = repo.find_commit(oid?)?;
+                                // public-inbox takes the author date from the
+                                // message's Date, so most of the archive is
+                                // skipped without reading the blob.
+                                let date = DateTime::from_timestamp(commit.author().when().seconds(), 0);
+                                if date.is_some_and(|date| {
+                                        self.config.since.is_some_and(|since| date < since)
+                                                || self.config.until.is_some_and(|until| date >= until)
+                                }) {
+                                        continue;
+                                }
+                                let Some(entry) = commit.tree()?.get_name("m").map(|entry| entry.id()) else {
+                                        continue;
+                                };
+                                let blob = repo.find_blob(entry)?;
+                                self.message(blob.content());
+                        }
+                }
+                Ok(())
+        }
+
+        /// Credits one raw RFC 5322 message. Messages that do not parse are
+        /// skipped; archives routinely contain a few.
+        pub(crate) fn message(&mut self, raw: &[u8]) {
+                let Ok(mail) = parse_mail(raw) else {
+                        return;
+                };
+                let headers = mail.get_headers();
+                let message_id = headers.get_first_value("Message-ID").unwrap_or_default().trim().to_string();
+                if !message_id.is_empty() && !self.seen.insert(message_id.clone()) {
+                        return;
+                }
+                let date = headers.get_first_value("Date")
+                        .and_then(|date| dateparse(&date).ok())
+                        .and_then(|secs| DateTime::from_timestamp(secs, 0));
+                if (self.config.since.is_some() || self.config.until.is_some()) && !date.is_some_and(|date| {
+                        self.config.since.is_none_or(|since| date >= since)
+                                && self.config.until.is_none_or(|until| date < until)
+                }) {
+                        return;
+                }
+                self.activity.messages += 1;
+
+                let subject = headers.get_first_value("Subject").unwrap_or_default();
+                let Some(from) = headers.get_first_value("From").and_then(|from| Identity::parse(&from)) else {
+                        return;
+                };
+                let from = self.resolver.resolve(from);
+                if !self.matcher.matches(&from.email) {
+                        return;
+                }
+                if !subject.trim_start().to_ascii_lowercase().starts_with("re:") {
+                        if is_patch(&subject) {
+                                self.activity.patches += 1;
+                        }
+                        return;
+                }
+
+                let body = text_body(&mail).unwrap_or_default();
+                let tags = self.review_tags(&body);
+                let comments = has_inline_comments(&body, &self.config.trailer_kinds);
+                if tags.is_empty() && !comments {
+                        return;
+                }
+                for &kind in &tags {
+                        self.activity.tags[kind] += 1;
+                }
+                if comments {
+                        self.activity.comments += 1;
+                }
+                self.activity.replies.push(ListReply {
+                        message_id,
+                        date,
+                        from,
+                        subject: subject.split_whitespace().collect::<Vec<_>>().join(" "),
+                        tags: tags.iter().map(|&kind| self.config.trailer_kinds[kind].key.clone()).collect(),
+                        comments,
+                });
+        }
+
+        /// Indices of the identity kinds given as unquoted `Key: identity`
+        /// lines naming a target, each at most once.
+        fn review_tags(&self, body: &str) -> Vec<usize> {
+                let mut tags = Vec::new();
+                for line in unquoted(body) {
+                        let Some((key, value)) = line.split_once(':') else {
+                                continue;
+                        };
+                        let Some(kind) = self.config.trailer_kinds.iter().position(|kind| {
+                                kind.mode == TrailerMatch::Identity && kind.key.eq_ignore_ascii_case(key.trim())
+                        }) else {
+                                continue;
+                        };
+                        let Some(identity) = Identity::parse(value.trim()) else {
+                                continue;
+                        };
+                        if self.matcher.matches(&self.resolver.resolve(identity).email) && !tags.contains(&kind) {
+                                tags.push(kind);
+                        }
+                }
+                tags
+        }
+}
+
+/// The epoch repositories of a public-inbox v2 archive in number order, or
+/// `path` itself when it is a bare repository.
+fn epochs(path: &Path) -> Result<Vec<PathBuf>> {
+        if path.join("HEAD").is_file() && path.join("objects").is_dir() {
+                return Ok(vec![path.to_path_buf()]);
+        }
+        let dir = path.join("git");
+        let mut epochs = Vec::new();
+        let entries = fs::read_dir(&dir)
+                .with_context(|| format!("{:?} is not a public-inbox v2 archive", path))?;
+        for entry in entries {
+                let epoch = entry?.path();
+                let number = epoch.file_name()
+                        .and_then(|name| name.to_str())
+                        .and_then(|name| name.strip_suffix(".git"))
+                        .and_then(|number| number.parse::<u32>().ok());
+                if let Some(number) = number {
+                        epochs.push((number, epoch));
+                }
+        }
+        if epochs.is_empty() {
+                bail!("No epoch repositories in {:?}", dir);
+        }
+        epochs.sort();
+        Ok(epochs.into_iter().map(|(_, epoch)| epoch).collect())
+}
+
+/// Whether `subject` posts a patch, e.g. `[PATCH v2 3/5]` or `[RFC PATCH]`,
+/// as opposed to a series' cover letter (`0/5`).
+fn is_patch(subject: &str) -> bool {
+        let Some((tag, _)) = subject.trim_start().strip_prefix('[').and_then(|rest| rest.split_once(']')) else {
+                return false;
+        };
+        let words: Vec<&str> = tag.split_whitespace().collect();
+        words.iter().any(|word| word.eq_ignore_ascii_case("PATCH"))
+                && !words.iter().any(|word| word.split_once('/').is_some_and(|(n, _)| n.bytes().all(|b| b == b'0')))
+}
+
+/// Body lines above the signature that are not quoted.
+fn unquoted(body: &str) -> impl Iterator<Item = &str> {
+        body.lines()
+                .take_while(|line| *line != "-- ")
+                .filter(|line| !line.starts_with('>'))
+}
+
+/// Whether the reviewer answered inline: their own text sits between two
+/// quoted lines of a patch. Text after the last quoted hunk ("Applied,
+/// thanks!") and replies to quoted prose such as a cover letter's bullet
+/// list do not count.
+fn has_inline_comments(body: &str, kinds: &[TrailerKind]) -> bool {
+        let mut in_hunk = false;
+        let mut commented = false;
+        for line in body.lines().take_while(|line| *line != "-- ") {
+                if let Some(quoted) = line.strip_prefix('>') {
+                        let quoted = quoted.strip_prefix(' ').unwrap_or(quoted);
+                        if quoted.starts_with("diff --git") || quoted.starts_with("@@") {
+                                in_hunk = true;
+                        } else if !(quoted.is_empty() || is_hunk_line(quoted)) {
+                                in_hunk = false;
+                        }
+                        if in_hunk && commented {
+                                return true;
+                        }
+                        continue;
+                }
+                let line = line.trim();
+                if !in_hunk || line.is_empty() {
+                        continue;
+                }
+                let is_tag = line.split_once(':').is_some_and(|(key, _)| {
+                        let key = key.trim();
+                        key.to_ascii_lowercase().ends_with("-by") || kinds.iter().any(|kind| kind.key.eq_ignore_ascii_case(key))
+                });
+                commented |= !is_tag;
+        }
+        false
+}
+
+/// A line of a diff after its `diff --git` header: file headers, hunk
+/// lines and context.
+fn is_hunk_line(line: &str) -> bool {
+        line.starts_with(['+', '-', ' ', '\\'])
+                || ["index ", "new file", "deleted file", "old mode", "new mode", "similarity", "rename ", "Binary files"]
+                        .iter()
+                        .any(|header| line.starts_with(header))
+}
+
+/// The first `text/plain` part, or the whole body of a single-part text
+/// message.
+fn text_body(mail: &ParsedMail) -> Option<String> {
+        if mail.subparts.is_empty() {
+                let mimetype = &mail.ctype.mimetype;
+                return (mimetype.is_empty() || mimetype.starts_with("text/plain"))
+                        .then(|| mail.get_body().ok())
+                        .flatten();
+        }
+        mail.subparts.iter().find_map(text_body)
+}
+
+/// Splits an mbox into its messages at `From ` separator lines, undoing
+/// the `>From ` quoting of mboxrd and mboxo.
+fn split_mbox(data: &[u8]) -> Vec<Vec<u8>> {
+        let mut messages = Vec::new();
+        let mut current: Option<Vec<u8>> = None;
+        let mut previous_blank = true;
+        for line in data.split_inclusive(|&b| b == b'\n') {
+                if previous_blank && line.starts_with(b"From ") {
+                        messages.extend(current.take());
+                        current = Some(Vec::new());
+                        previous_blank = false;
+                        continue;
+                }
+                previous_blank = line == b"\n" || line == b"\r\n";
+                let Some(message) = current.as_mut() else {
+                        continue;
+                };
+                let quotes = line.iter().take_while(|&&b| b == b'>').count();
+                if quotes > 0 && line[quotes..].starts_with(b"From ") {
+                        message.extend_from_slice(&line[1..]);
+                } else {
+                        message.extend_from_slice(line);
+                }
+        }
+        messages.extend(current);
+        messages
+}
+
+#[cfg(test)]
+mod tests {
+        use super::*;
+
+        fn comments(body: &str) -> bool {
+                has_inline_comments(body, &TrailerKind::defaults())
+        }
+
+        #[test]
+        fn split_mbox_unquotes_from_lines() {
+                let mbox = b"From a@x Mon Jan 1 00:00:00 2024\nSubject: one\n\n>From here\n>>From there\n\nFrom b@x Mon Jan 1 00:00:00 2024\nSubject: two\n";
+                let messages = split_mbox(mbox);
+                assert_eq!(messages.len(), 2);
+                assert_eq!(messages[0], b"Subject: one\n\nFrom here\n>From there\n\n");
+                assert_eq!(messages[1], b"Subject: two\n");
+        }
+
+        #[test]
+        fn split_mbox_needs_blank_line_before_separator() {
+                let mbox = b"From a@x Mon Jan 1 00:00:00 2024\nSubject: one\nFrom b@x\n";
+                assert_eq!(split_mbox(mbox).len(), 1);
+        }
+
+        #[test]
+        fn interleaved_reply_is_an_inline_comment() {
+                let body = "> diff --git a/f b/f\n> @@ -1 +1 @@\n> -a\n\nWhy remove this?\n\n> +b\n";
+                assert!(comments(body));
+        }
+
+        #[test]
+        fn text_after_the_last_hunk_is_not_an_inline_comment() {
+                let body = "> diff --git a/f b/f\n> @@ -1 +1 @@\n> -a\n> +b\n\nApplied, thanks!\n";
+                assert!(!comments(body));
+        }
+
+        #[test]
+        fn tags_between_hunks_are_not_comments() {
+                let body = "> @@ -1 +1 @@\n> -a\nReviewed-by: Bob <bob@x.org>\n> +b\n";
+                assert!(!comments(body));
+        }
+
+        #[test]
+        fn replies_to_quoted_bullets_are_not_inline_comments() {
+                let body = "> This series:\n> - adds a\n\nNice.\n\n> - removes b\n> ---\n";
+                assert!(!comments(body));
+        }
+
+        #[test]
+        fn patches_but_not_cover_letters() {
+                assert!(is_patch("[PATCH] arm: fix thing"));
+                assert!(is_patch("[RFC PATCH v2 3/5] arm: fix thing"));
+                assert!(is_patch("[patch 1/10] x"));
+                assert!(!is_patch("[PATCH v3 0/2] arm: series"));
+                assert!(!is_patch("[PATCH 00/10] arm: series"));
+                assert!(!is_patch("Re: [PATCH] arm: fix thing"));
+                assert!(!is_patch("[GIT PULL] arm fixes"));
+        }
+}
@@ -1,22 +1,112 @@
-use anyhow::{Context, Result};
-use chrono::{DateTime, NaiveDate, Utc, TimeZone};
-use clap::Parser;
-use git2::{Repository, Sort};
-use std::path::PathBuf;
-
-use charming::{
-        Chart, ImageRenderer, ImageFormat,
-        component::{Legend, Title,},
-        element::{ItemStyle, Label, LabelPosition},
-        series::{Pie},
-        theme::Theme,
-};
+use anyhow::{Context, Result, anyhow, bail};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use std::path::{Path, PathBuf};
+
+use git_stats::{ActivityRow, Bucket, ContributionReport, DateField, ListActivity, Maintainers, MergeMode, MatchedCommit, Organizations, Role, ScanConfig, TrailerKind, TrailerMatch, UpstreamPatch, combined_name, find_repositories, parse_date, parse_end_date, repository_names, scan};
+use git_stats::config::{ConfigFile, DEFAULT_CONFIG, MaintainersSource, ReportDefinition};
+use charming::theme::Theme;
+use git_stats::chart::{ChartFormat, ChartOptions, TrendStyle, generate_bar_chart, generate_pie_chart, generate_trend_chart};
+use git_stats::csv::to_csv;
+use git_stats::diffstat::{DiffStat, parse_excludes};
+use git_stats::json::to_json;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+        /// Aligned, human-readable summary
+        Text,
+        /// Versioned JSON document for dashboards
+        Json,
+        /// One CSV row per matched commit
+        Csv,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum DateArg {
+        /// Committer date, as shown by git log
+        Committer,
+        /// Author date, kept across rebases and cherry-picks
+        Author,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum MergeArg {
+        /// Count merges like any other commit
+        Include,
+        /// Skip merges entirely
+        Exclude,
+        /// Count merges as their own "Merged" category
+        Separate,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum PeopleChart {
+        /// One pie chart per person
+        Pie,
+        /// A single grouped bar chart with one group per person
+        Bar,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum BucketArg {
+        /// ISO weeks, starting on Monday
+        Week,
+        Month,
+        Quarter,
+        Year,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum TrendChart {
+        /// Stacked bars, one per bucket
+        Bar,
+        /// One line per category
+        Line,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ChartFormatArg {
+        Png,
+        Svg,
+        /// Interactive page rendered by ECharts in the browser
+        Html,
+}
+
+/// The ECharts themes bundled with charming.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ChartTheme {
+        Default,
+        Dark,
+        Vintage,
+        Westeros,
+        Essos,
+        Wonderland,
+        Walden,
+        Chalk,
+        Infographic,
+        Macarons,
+        Roma,
+        Shine,
+        PurplePassion,
+        Halloween,
+}
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
+        /// Take options from this named report in the config file; options
+        /// given on the command line override it
+        #[arg(long, value_name = "NAME")]
+        report: Option<String>,
+
+        /// TOML file defining the reports [default: git_stats.toml]
+        #[arg(long, value_name = "FILE", requires = "report")]
+        config: Option<PathBuf>,
+
+        /// Repository to scan, or a directory searched recursively for
+        /// repositories; may be given several times
         #[arg(short, long, default_value = ".")]
-        path: PathBuf,
+        path: Vec<PathBuf>,
 
         #[arg(short, long)]
         email: Vec<String>,
@@ -24,209 +114,719 @@ struct Args {
         #[arg(short, long)]
         since: Option<String>,
 
+        /// Last day (YYYY-MM-DD, inclusive) to count commits from
+        #[arg(short, long)]
+        until: Option<String>,
+
+        /// Which commit date --since and --until compare against
+        #[arg(long, value_enum, default_value_t = DateArg::Committer)]
+        date: DateArg,
+
+        /// Revision or range to walk instead of HEAD, e.g. v2024.01..v2024.04
+        #[arg(short, long = "range", value_name = "REV")]
+        revisions: Vec<String>,
+
+        /// How merge commits are counted
+        #[arg(long, value_enum, default_value_t = MergeArg::Include)]
+        merges: MergeArg,
+
+        /// Follow only the first parent of merges
+        #[arg(long, default_value_t = false, overrides_with = "no_first_parent")]
+        first_parent: bool,
+
+        /// Undo --first-parent, e.g. when set by a report
         #[arg(long, default_value_t = false)]
+        no_first_parent: bool,
+
+        /// Upstream revision or range to look for the targets' patches in, e.g. v6.1..v6.8
+        #[arg(long = "upstream-range", value_name = "REV")]
+        upstream_ranges: Vec<String>,
+
+        /// Count commits as committed only if they carry the committer's Signed-off-by
+        #[arg(long, default_value_t = false, overrides_with = "no_require_signoff")]
+        require_signoff: bool,
+
+        /// Undo --require-signoff, e.g. when set by a report
+        #[arg(long, default_value_t = false)]
+        no_require_signoff: bool,
+
+        /// Walk all local branches
+        #[arg(long, default_value_t = false, overrides_with = "no_branches")]
+        branches: bool,
+
+        /// Undo --branches, e.g. when set by a report
+        #[arg(long, default_value_t = false)]
+        no_branches: bool,
+
+        /// Walk all remote-tracking branches
+        #[arg(long, default_value_t = false, overrides_with = "no_remotes")]
+        remotes: bool,
+
+        /// Undo --remotes, e.g. when set by a report
+        #[arg(long, default_value_t = false)]
+        no_remotes: bool,
+
+        /// Walk all tags
+        #[arg(long, default_value_t = false, overrides_with = "no_tags")]
+        tags: bool,
+
+        /// Undo --tags, e.g. when set by a report
+        #[arg(long, default_value_t = false)]
+        no_tags: bool,
+
+        /// Walk refs matching a glob, e.g. refs/heads/release-*
+        #[arg(long = "glob", value_name = "PATTERN")]
+        globs: Vec<String>,
+
+        #[arg(long, default_value_t = false, overrides_with = "no_partial")]
         partial: bool,
 
+        /// Undo --partial, e.g. when set by a report
         #[arg(long, default_value_t = false)]
+        no_partial: bool,
+
+        #[arg(long, default_value_t = false, overrides_with = "no_verbose")]
         verbose: bool,
+
+        /// Undo --verbose, e.g. when set by a report
+        #[arg(long, default_value_t = false)]
+        no_verbose: bool,
+
+        /// Extra trailer kind to count, as Key[:Label[:identity|not-author|authored]]
+        #[arg(long = "trailer", value_name = "SPEC")]
+        trailers: Vec<String>,
+
+        /// File with one trailer spec per line, replacing the default kinds
+        #[arg(long, value_name = "FILE")]
+        trailers_file: Option<PathBuf>,
+
+        /// File mapping email domains and addresses to organizations
+        #[arg(long, value_name = "FILE")]
+        orgs_file: Option<PathBuf>,
+
+        /// Count everyone the organization file places in this organization
+        #[arg(long = "org", value_name = "NAME")]
+        orgs: Vec<String>,
+
+        /// Extra mailmap file applied on top of the repository's .mailmap
+        #[arg(long, value_name = "FILE")]
+        mailmap: Option<PathBuf>,
+
+        /// Compare identities as recorded, ignoring any mailmap
+        #[arg(long, default_value_t = false, conflicts_with = "mailmap")]
+        no_mailmap: bool,
+
+        /// Also chart the per-person breakdown
+        #[arg(long, value_enum, value_name = "KIND")]
+        people_chart: Option<PeopleChart>,
+
+        /// Group activity into time buckets to show trends
+        #[arg(long, value_enum, value_name = "INTERVAL")]
+        bucket: Option<BucketArg>,
+
+        /// How to chart the time series
+        #[arg(long, value_enum, value_name = "KIND", default_value_t = TrendChart::Bar)]
+        trend_chart: TrendChart,
+
+        /// mbox file or maildir to count review replies from (repeatable)
+        #[arg(long, value_name = "PATH")]
+        mail_archive: Vec<PathBuf>,
+
+        /// public-inbox v2 archive, e.g. a lore mirror, to count patches and
+        /// review replies from (repeatable)
+        #[arg(long, value_name = "PATH")]
+        public_inbox: Vec<PathBuf>,
+
+        /// Directory for the charts, or the main chart's file name
+        #[arg(long, value_name = "PATH")]
+        chart_output: Option<PathBuf>,
+
+        /// Chart file format [default: from --chart-output, else png]
+        #[arg(long, value_enum, value_name = "FORMAT")]
+        chart_format: Option<ChartFormatArg>,
+
+        /// Chart width in pixels
+        #[arg(long, value_name = "PIXELS")]
+        chart_width: Option<u32>,
+
+        /// Chart height in pixels
+        #[arg(long, value_name = "PIXELS")]
+        chart_height: Option<u32>,
+
+        /// ECharts theme for the charts
+        #[arg(long, value_enum, value_name = "THEME", default_value_t = ChartTheme::Shine)]
+        chart_theme: ChartTheme,
+
+        /// Do not write any charts
+        #[arg(long, default_value_t = false, overrides_with = "chart")]
+        no_chart: bool,
+
+        /// Write the charts even when a report sets no-chart
+        #[arg(long, default_value_t = false)]
+        chart: bool,
+
+        /// Only scan commits touching this path, e.g. drivers/firmware/
+        #[arg(long, value_name = "PATHSPEC")]
+        path_filter: Vec<String>,
+
+        /// Attribute commits to subsystems using a MAINTAINERS file (default: the
+        /// repository's own MAINTAINERS at HEAD)
+        #[arg(long, value_name = "FILE", num_args = 0..=1)]
+        maintainers: Option<Option<PathBuf>>,
+
+        /// Compute files changed, insertions and deletions of matched commits
+        #[arg(long, default_value_t = false, overrides_with = "no_diffstat")]
+        diffstat: bool,
+
+        /// Undo --diffstat, e.g. when set by a report
+        #[arg(long, default_value_t = false)]
+        no_diffstat: bool,
+
+        /// Leave paths matching this glob out of diffstats, e.g. 'vendor/**'
+        #[arg(long, value_name = "GLOB")]
+        exclude_path: Vec<String>,
+
+        /// How results are written to stdout
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+
+        /// List every matched commit and its roles in the JSON output
+        #[arg(long, default_value_t = false, overrides_with = "no_include_commits")]
+        include_commits: bool,
+
+        /// Undo --include-commits, e.g. when set by a report
+        #[arg(long, default_value_t = false)]
+        no_include_commits: bool,
 }
 
 fn main() -> Result<()> {
-        let args = Args::parse();
-
-        // 1. Parse Date
-        let since_date = if let Some(date_str) = &args.since {
-                let naive_date = NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
-                        .context("Invalid date format. Please use YYYY-MM-DD")?;
-                Some(Utc.from_utc_datetime(&naive_date.and_hms_opt(0, 0, 0).unwrap()))
-        } else {
-                None
+        let matches = Args::command().get_matches();
+        let mut args = Args::from_arg_matches(&matches)?;
+        if let Some(name) = &args.report {
+                let file = args.config.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG));
+                let definition = ConfigFile::load(&file)?.report(name)?.clone();
+                apply_report(&mut args, &matches, &definition)?;
+        }
+        // Checked here rather than by clap, so a report can supply either side.
+        if !args.orgs.is_empty() && args.orgs_file.is_none() {
+                bail!("--org needs an organization map from --orgs-file");
+        }
+        if !args.exclude_path.is_empty() && !args.diffstat {
+                bail!("--exclude-path only applies with --diffstat");
+        }
+        if matches.value_source("trend_chart") == Some(ValueSource::CommandLine) && args.bucket.is_none() {
+                bail!("--trend-chart needs --bucket");
+        }
+
+        // 1. Parse Dates
+        let since_date = args.since.as_deref().map(parse_date).transpose()?;
+        let until_date = args.until.as_deref().map(parse_end_date).transpose()?;
+
+        let mut trailer_kinds = match &args.trailers_file {
+                Some(file) => TrailerKind::load_file(file)?,
+                None => TrailerKind::defaults(),
         };
+        for spec in &args.trailers {
+                let kind = TrailerKind::parse_spec(spec)?;
+                trailer_kinds.retain(|k| !k.key.eq_ignore_ascii_case(&kind.key));
+                trailer_kinds.push(kind);
+        }
 
-        // 2. Open Repo
-        let repo = Repository::open(&args.path)
-                .with_context(|| format!("Failed to open git repository at {:?}", args.path))?;
+        // Overlapping searches (`-p ~/src -p ~/src/linux`) find the same
+        // repository twice; it is scanned once.
+        let mut paths = Vec::new();
+        for path in &args.path {
+                for repo in find_repositories(path)? {
+                        paths.push(repo.canonicalize().with_context(|| format!("Failed to resolve {:?}", repo))?);
+                }
+        }
+        paths.sort();
+        paths.dedup();
+
+        let maintainers = match &args.maintainers {
+                Some(Some(file)) => Some(Maintainers::load(file)?),
+                Some(None) => match paths.as_slice() {
+                        [path] => Some(Maintainers::from_repository(path)?),
+                        _ => bail!("--maintainers needs a FILE when scanning several repositories"),
+                },
+                None => None,
+        };
 
-        let mut revwalk = repo.revwalk().context("Failed to initialize revision walker")?;
-        revwalk.push_head().context("Failed to find HEAD")?;
-        revwalk.set_sorting(Sort::TIME)?;
+        let config = ScanConfig {
+                paths,
+                emails: args.email.clone(),
+                since: since_date,
+                until: until_date,
+                date_field: match args.date {
+                        DateArg::Committer => DateField::Committer,
+                        DateArg::Author => DateField::Author,
+                },
+                revisions: args.revisions.clone(),
+                ref_globs: ref_globs(&args),
+                merges: match args.merges {
+                        MergeArg::Include => MergeMode::Include,
+                        MergeArg::Exclude => MergeMode::Exclude,
+                        MergeArg::Separate => MergeMode::Separate,
+                },
+                first_parent: args.first_parent,
+                require_signoff: args.require_signoff,
+                partial: args.partial,
+                trailer_kinds,
+                organizations: match &args.orgs_file {
+                        Some(file) => Organizations::load(file)?,
+                        None => Organizations::default(),
+                },
+                orgs: args.orgs.clone(),
+                use_mailmap: !args.no_mailmap,
+                mailmap_file: args.mailmap.clone(),
+                path_filters: args.path_filter.clone(),
+                maintainers,
+                diffstat: args.diffstat,
+                exclude_paths: parse_excludes(&args.exclude_path)?,
+                mail_archives: args.mail_archive.clone(),
+                public_inboxes: args.public_inbox.clone(),
+                upstream_ranges: args.upstream_ranges.clone(),
+                bucket: args.bucket.map(|bucket| match bucket {
+                        BucketArg::Week => Bucket::Week,
+                        BucketArg::Month => Bucket::Month,
+                        BucketArg::Quarter => Bucket::Quarter,
+                        BucketArg::Year => Bucket::Year,
+                }),
+        };
 
-        println!("Scanning repository: {:?}", args.path.canonicalize()?);
-        println!("Target Emails:       {}", args.email.join(", "));
-        if let Some(d) = since_date {
-                println!("Timeframe:           Since {}", d.format("%Y-%m-%d"));
+        if args.format == OutputFormat::Text {
+                print_header(&args, &config)?;
         }
-        println!("------------------------------------------------");
 
-        let mut commits_authored = 0;
-        let mut total_scanned = 0;
+        // 2. Walk the repo
+        let report = scan(&config)?;
 
-        let mut reviewed_count = 0;
-        let mut acked_count = 0;
-        let mut tested_count = 0;
-        let mut reported_count = 0;
+        match args.format {
+                OutputFormat::Text => print_text_report(&args, &config, &report),
+                OutputFormat::Json => println!("{}", to_json(&config, &report, args.include_commits)?),
+                OutputFormat::Csv => print!("{}", to_csv(&report)),
+        }
 
-        let search_emails: Vec<String> = args.email.iter().map(|e| e.to_lowercase()).collect();
+        if report.total_scanned > 0 && !args.no_chart {
+                generate_charts(&args, &config, &report)?;
+        }
 
-        for oid in revwalk {
-                total_scanned += 1;
-                let oid = oid.context("Failed to get object ID")?;
-                let commit = repo.find_commit(oid).context("Failed to find commit")?;
+        Ok(())
+}
 
-                let seconds = commit.time().seconds();
-                let commit_time = DateTime::from_timestamp(seconds, 0).unwrap_or_default();
+/// Fills in every option not given on the command line from `report`.
+/// Repeatable options given on the command line replace the report's list
+/// rather than adding to it, and a report's switches are turned off again
+/// with their `--no-` forms (`--chart` for `no-chart`).
+fn apply_report(args: &mut Args, matches: &ArgMatches, report: &ReportDefinition) -> Result<()> {
+        let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
 
-                if let Some(since) = since_date {
-                        if commit_time < since { break; }
+        if !from_cli("path") && !report.paths.is_empty() {
+                args.path = report.paths.clone();
+        }
+        if args.email.is_empty() {
+                args.email = report.emails.clone();
+        }
+        if args.orgs.is_empty() {
+                args.orgs = report.orgs.clone();
+        }
+        args.orgs_file = args.orgs_file.take().or(report.orgs_file.clone());
+        args.partial |= report.partial && !args.no_partial;
+        args.mailmap = args.mailmap.take().or(report.mailmap.clone());
+        args.no_mailmap |= report.no_mailmap && args.mailmap.is_none();
+        args.since = args.since.take().or(report.since.clone());
+        args.until = args.until.take().or(report.until.clone());
+        if !from_cli("date") && let Some(date) = value("date", &report.date)? {
+                args.date = date;
+        }
+        if args.revisions.is_empty() {
+                args.revisions = report.ranges.clone();
+        }
+        args.branches |= report.branches && !args.no_branches;
+        args.remotes |= report.remotes && !args.no_remotes;
+        args.tags |= report.tags && !args.no_tags;
+        if args.globs.is_empty() {
+                args.globs = report.globs.clone();
+        }
+        if !from_cli("merges") && let Some(merges) = value("merges", &report.merges)? {
+                args.merges = merges;
+        }
+        args.first_parent |= report.first_parent && !args.no_first_parent;
+        args.require_signoff |= report.require_signoff && !args.no_require_signoff;
+        if args.upstream_ranges.is_empty() {
+                args.upstream_ranges = report.upstream_ranges.clone();
+        }
+        if args.trailers.is_empty() {
+                args.trailers = report.trailers.clone();
+        }
+        args.trailers_file = args.trailers_file.take().or(report.trailers_file.clone());
+        if args.path_filter.is_empty() {
+                args.path_filter = report.path_filters.clone();
+        }
+        if args.maintainers.is_none() {
+                args.maintainers = match &report.maintainers {
+                        Some(MaintainersSource::File(file)) => Some(Some(file.clone())),
+                        Some(MaintainersSource::Repository(true)) => Some(None),
+                        Some(MaintainersSource::Repository(false)) | None => None,
+                };
+        }
+        args.diffstat |= report.diffstat && !args.no_diffstat;
+        if args.exclude_path.is_empty() {
+                args.exclude_path = report.exclude_paths.clone();
+        }
+        args.bucket = args.bucket.or(value("bucket", &report.bucket)?);
+        if !from_cli("trend_chart") && let Some(chart) = value("trend-chart", &report.trend_chart)? {
+                args.trend_chart = chart;
+        }
+        args.people_chart = args.people_chart.or(value("people-chart", &report.people_chart)?);
+        if !from_cli("format") && let Some(format) = value("format", &report.format)? {
+                args.format = format;
+        }
+        args.include_commits |= report.include_commits && !args.no_include_commits;
+        if args.mail_archive.is_empty() {
+                args.mail_archive = report.mail_archives.clone();
+        }
+        if args.public_inbox.is_empty() {
+                args.public_inbox = report.public_inboxes.clone();
+        }
+        args.chart_output = args.chart_output.take().or(report.chart_output.clone());
+        args.chart_format = args.chart_format.or(value("chart-format", &report.chart_format)?);
+        args.chart_width = args.chart_width.or(report.chart_width);
+        args.chart_height = args.chart_height.or(report.chart_height);
+        if !from_cli("chart_theme") && let Some(theme) = value("chart-theme", &report.chart_theme)? {
+                args.chart_theme = theme;
+        }
+        args.no_chart |= report.no_chart && !args.chart;
+        args.verbose |= report.verbose && !args.no_verbose;
+        Ok(())
+}
+
+/// Parses a report's value for `key` like the command line would.
+fn value<T: ValueEnum>(key: &str, value: &Option<String>) -> Result<Option<T>> {
+        value.as_deref()
+                .map(|v| T::from_str(v, true).map_err(|_| anyhow!("Invalid {} {:?} in report", key, v)))
+                .transpose()
+}
+
+fn ref_globs(args: &Args) -> Vec<String> {
+        let mut globs = Vec::new();
+        if args.branches {
+                globs.push("refs/heads/*".to_string());
+        }
+        if args.remotes {
+                globs.push("refs/remotes/*".to_string());
+        }
+        if args.tags {
+                globs.push("refs/tags/*".to_string());
+        }
+        globs.extend(args.globs.iter().cloned());
+        globs
+}
+
+fn print_header(args: &Args, config: &ScanConfig) -> Result<()> {
+        match config.paths.as_slice() {
+                [path] => println!("Scanning repository: {:?}", path.canonicalize()?),
+                paths => println!("Scanning {} repositories: {}", paths.len(), repository_names(paths)?.join(", ")),
+        }
+        println!("Target Emails:       {}", args.email.join(", "));
+        if !args.orgs.is_empty() {
+                println!("Target Orgs:         {}", args.orgs.join(", "));
+        }
+        if config.since.is_some() || config.until.is_some()
+                || !config.revisions.is_empty() || !config.ref_globs.is_empty() {
+                println!("Timeframe:           {}", config.range_label());
+        }
+        if !config.path_filters.is_empty() {
+                println!("Paths:               {}", config.path_filters.join(", "));
+        }
+        println!("------------------------------------------------");
+        Ok(())
+}
+
+fn print_text_report(args: &Args, config: &ScanConfig, report: &ContributionReport) {
+        if args.verbose {
+                for commit in report.commits.iter().filter(|c| c.has_role(&Role::Author)) {
+                        print_commit(commit);
+                }
+                for unparsed in &report.unparsed_trailers {
+                        println!("{} | no email in \"{}: {}\"", &unparsed.commit.to_string()[0..7],
+                                unparsed.trailer.key, unparsed.trailer.value);
                 }
+        }
+
+        print_summary(report);
+        let labels = report.activity_labels();
+        if config.emails.len() + config.orgs.len() > 1 || config.partial {
+                print_table("People", &labels, &report.people, Some(&report.people_total()));
+        }
+        if report.repositories.len() > 1 {
+                print_table("Repositories", &labels, &report.repositories, Some(&report.repositories_total()));
+        }
+        print_table("Organizations", &labels, &report.organizations, None);
+        print_table("Subsystems", &labels, &report.subsystems, None);
+        if !report.subsystems.is_empty() && report.people.len() > 1 {
+                println!("\nEffort by subsystem:");
+                for person in report.people.iter().filter(|p| !p.subsystems.is_empty()) {
+                        let top: Vec<String> = person.subsystems.iter().take(5)
+                                .map(|(name, count)| format!("{} ({})", name, count))
+                                .collect();
+                        println!("{}: {}", person.name, top.join(", "));
+                }
+        }
+        if let Some(bucket) = args.bucket {
+                let heading = format!("Activity per {}", bucket.to_possible_value().unwrap().get_name());
+                print_table(&heading, &labels, &report.timeline, None);
+        }
+        if config.diffstat {
+                print_diffstats(report);
+        }
+        if let Some(list) = &report.mailing_list {
+                print_mailing_list(args, report, list);
+        }
+        if let Some(patches) = &report.upstream {
+                print_upstream(report, patches);
+        }
+}
 
-                let author = commit.author();
-                if let Some(author_email) = author.email() {
-                        let is_match = if args.partial {
-                                search_emails.iter().any(|email| author_email.contains(email))
-                        } else {
-                                search_emails.iter().any(|email| author_email == email)
-                        };
-                        if is_match {
-                                if args.verbose {
-                                        print_commit(&commit, &commit_time);
+fn generate_charts(args: &Args, config: &ScanConfig, report: &ContributionReport) -> Result<()> {
+        let mut data = report.involvement();
+        data.push((untouched_label(config), report.no_interaction()));
+        let options = chart_options(args, config)?;
+        let pdate = config.range_label();
+        println!("Generating charts...");
+        generate_pie_chart(&options, "", &pdate, data)?;
+        if !report.organizations.is_empty() {
+                let org_data = report.organizations.iter()
+                        .map(|org| (org.name.clone(), org.total()))
+                        .collect();
+                generate_pie_chart(&options, "organizations", &pdate, org_data)?;
+        }
+        if report.repositories.len() > 1 {
+                let repo_data = top_slices(report.repositories.iter()
+                        .map(|repo| (repo.name.clone(), repo.total())).collect());
+                generate_pie_chart(&options, "repositories", &pdate, repo_data)?;
+        }
+        if !report.timeline.is_empty() {
+                let buckets = report.timeline.iter().map(|row| row.name.clone()).collect();
+                let series = report.activity_labels().into_iter().enumerate()
+                        .map(|(i, label)| (label, report.timeline.iter().map(|row| row.counts()[i]).collect()))
+                        .collect();
+                let style = match args.trend_chart {
+                        TrendChart::Bar => TrendStyle::StackedBar,
+                        TrendChart::Line => TrendStyle::Line,
+                };
+                generate_trend_chart(&options, "trend", &pdate, buckets, series, style)?;
+        }
+        if !report.subsystems.is_empty() {
+                let subsystem_data = top_slices(report.subsystems.iter()
+                        .map(|s| (s.name.clone(), s.total())).collect());
+                generate_pie_chart(&options, "subsystems", &pdate, subsystem_data)?;
+        }
+        match args.people_chart {
+                Some(PeopleChart::Pie) => {
+                        for person in &report.people {
+                                let person_data = report.activity_labels().into_iter()
+                                        .zip(person.counts())
+                                        .collect();
+                                generate_pie_chart(&options, &person.name, &pdate, person_data)?;
+                                if !person.subsystems.is_empty() {
+                                        generate_pie_chart(&options, &format!("{} subsystems", person.name),
+                                                &pdate, top_slices(person.subsystems.clone()))?;
                                 }
-                                commits_authored += 1;
                         }
                 }
-
-                if let Some(msg) = commit.message() {
-                        analyze_trailers(msg, &search_emails, &mut reviewed_count, &mut acked_count, &mut tested_count, &mut reported_count);
+                Some(PeopleChart::Bar) => {
+                        let names: Vec<String> = report.people.iter().map(|p| p.name.clone()).collect();
+                        let series = report.activity_labels().into_iter().enumerate()
+                                .map(|(i, label)| (label, report.people.iter().map(|p| p.counts()[i]).collect()))
+                                .collect();
+                        generate_bar_chart(&options, "people", &pdate, names.clone(), series)?;
+                        if !report.subsystems.is_empty() {
+                                let series = report.subsystems.iter().take(MAX_SLICES)
+                                        .map(|s| (s.name.clone(), report.people.iter()
+                                                .map(|p| p.subsystems.iter().find(|(n, _)| *n == s.name).map_or(0, |(_, c)| *c))
+                                                .collect()))
+                                        .collect();
+                                generate_bar_chart(&options, "people subsystems", &pdate, names, series)?;
+                        }
                 }
+                None => {}
         }
+        Ok(())
+}
 
-        println!("\nSummary:");
-        println!("Total Scanned: {}", total_scanned);
-        println!("Authored:      {}", commits_authored);
-        println!("Reviewed:      {}", reviewed_count);
-        println!("Acked:         {}", acked_count);
-        println!("Tested:        {}", tested_count);
-        println!("Reported:      {}", reported_count);
-
-        println!("Generating Pie Charts...");
-
-        if total_scanned > 0 {
-                let total_activity = reviewed_count + acked_count + tested_count +
-                        reported_count + commits_authored;
-                let no_interaction = if total_scanned > total_activity {
-                        total_scanned - total_activity
+/// Chart options from the command line. `--chart-output` names either a
+/// directory, when it is one or ends in a separator, or the main chart's
+/// file, whose extension then also picks the format.
+fn chart_options(args: &Args, config: &ScanConfig) -> Result<ChartOptions> {
+        let mut options = ChartOptions::new(&combined_name(&config.paths)?);
+        if let Some(output) = &args.chart_output {
+                let is_dir = output.is_dir() || output.as_os_str().to_string_lossy().ends_with(std::path::MAIN_SEPARATOR);
+                if is_dir {
+                        options.dir = output.clone();
                 } else {
-                        0
+                        options.dir = output.parent().map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+                        if let Some(stem) = output.file_stem() {
+                                options.stem = stem.to_string_lossy().into_owned();
+                        }
+                        if let Some(format) = ChartFormat::from_path(output) {
+                                options.format = format;
+                        }
+                }
+        }
+        if let Some(format) = args.chart_format {
+                options.format = match format {
+                        ChartFormatArg::Png => ChartFormat::Png,
+                        ChartFormatArg::Svg => ChartFormat::Svg,
+                        ChartFormatArg::Html => ChartFormat::Html,
                 };
+        }
+        options.width = args.chart_width;
+        options.height = args.chart_height;
+        options.theme = match args.chart_theme {
+                ChartTheme::Default => Theme::Default,
+                ChartTheme::Dark => Theme::Dark,
+                ChartTheme::Vintage => Theme::Vintage,
+                ChartTheme::Westeros => Theme::Westeros,
+                ChartTheme::Essos => Theme::Essos,
+                ChartTheme::Wonderland => Theme::Wonderland,
+                ChartTheme::Walden => Theme::Walden,
+                ChartTheme::Chalk => Theme::Chalk,
+                ChartTheme::Infographic => Theme::Infographic,
+                ChartTheme::Macarons => Theme::Macarons,
+                ChartTheme::Roma => Theme::Roma,
+                ChartTheme::Shine => Theme::Shine,
+                ChartTheme::PurplePassion => Theme::PurplePassion,
+                ChartTheme::Halloween => Theme::Halloween,
+        };
+        if !options.dir.as_os_str().is_empty() {
+                std::fs::create_dir_all(&options.dir)
+                        .with_context(|| format!("Failed to create chart directory {:?}", options.dir))?;
+        }
+        Ok(options)
+}
 
-                let data = vec![
-                        ("Authored", commits_authored),
-                        ("Reviewed", reviewed_count),
-                        ("Acked", acked_count),
-                        ("Tested", tested_count),
-                        ("Reported", reported_count),
-                        ("Non Linaro", no_interaction),
-                ];
-                if let Some(last_component) = args.path.file_name() {
-                        let title = last_component.to_string_lossy().into_owned();
-                        let pdate = if let Some(s) = &args.since {
-                                format!("{} -- Today", s)
-                        } else {
-                                "Overall".to_string()
-                        };
-                        generate_pie_chart(&title, &pdate, data)?;
+/// Subsystem charts keep the largest slices and fold the rest into "Other".
+const MAX_SLICES: usize = 10;
+
+fn top_slices(mut data: Vec<(String, usize)>) -> Vec<(String, usize)> {
+        data.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        if data.len() > MAX_SLICES {
+                let other = data.split_off(MAX_SLICES - 1).iter().map(|(_, count)| count).sum();
+                data.push(("Other".to_string(), other));
+        }
+        data
+}
+
+fn print_summary(report: &ContributionReport) {
+        println!("\nSummary:");
+        println!("Total Scanned: {}", report.total_scanned);
+        for (label, count) in report.activity() {
+                println!("{:<15}{}", format!("{}:", label), count);
+        }
+        let percent = |count: usize| 100.0 * count as f64 / report.total_scanned.max(1) as f64;
+        println!("{:<15}{} commits ({:.1}%)", "Involved:", report.involved(), percent(report.involved()));
+        println!("{:<15}{} commits ({:.1}%)", "Untouched:", report.no_interaction(), percent(report.no_interaction()));
+        if !report.unparsed_trailers.is_empty() {
+                println!("Unparsed:      {} (trailers without an email, see --verbose)", report.unparsed_trailers.len());
+        }
+}
+
+fn print_mailing_list(args: &Args, report: &ContributionReport, list: &ListActivity) {
+        if args.verbose {
+                println!();
+                for reply in &list.replies {
+                        let mut given = reply.tags.clone();
+                        if reply.comments {
+                                given.push("comments".to_string());
+                        }
+                        println!("{} | {} | {} | {}",
+                                reply.date.map_or("----------".to_string(), |d| d.format("%Y-%m-%d").to_string()),
+                                reply.from.email, reply.subject, given.join(", "));
                 }
         }
+        println!("\nMailing list:");
+        println!("{:<15}{}", "Messages:", list.messages);
+        println!("{:<15}{}", "Patches:", list.patches);
+        println!("{:<15}{}", "Replies:", list.replies.len());
+        for (trailer, count) in report.trailers.iter().zip(&list.tags) {
+                if trailer.kind.mode == TrailerMatch::Identity {
+                        println!("{:<15}{}", format!("{}:", trailer.kind.label), count);
+                }
+        }
+        println!("{:<15}{}", "Comments:", list.comments);
+}
 
-        Ok(())
+fn print_upstream(report: &ContributionReport, patches: &[UpstreamPatch]) {
+        let (landed, pending) = report.upstream_counts();
+        println!("\nUpstream:");
+        println!("{:<15}{}", "Landed:", landed);
+        println!("{:<15}{}", "Pending:", pending);
+        for patch in patches {
+                let status = match &patch.landed {
+                        Some(landing) => format!("{} as {} ({})",
+                                landing.release.as_deref().unwrap_or("unreleased"),
+                                &landing.id.to_string()[0..7], landing.matched_by.name()),
+                        None => "pending".to_string(),
+                };
+                println!("{} | {} | {} | {}", patch.short_id(), patch.date.format("%Y-%m-%d"), status, patch.summary);
+        }
 }
 
-fn analyze_trailers(msg: &str, targets: &[String], reviewed: &mut i32, acked: &mut i32, tested: &mut i32, reported: &mut i32) {
-        for line in msg.lines() {
-                let lower = line.trim().to_lowercase();
-                if targets.iter().any(|target| lower.contains(target)) {
-                        if lower.starts_with("reviewed-by:") { *reviewed += 1; }
-                        else if lower.starts_with("acked-by:") { *acked += 1; }
-                        else if lower.starts_with("tested-by:") { *tested += 1; }
-                        else if lower.starts_with("reported-by:") { *reported += 1; }
+fn print_table(heading: &str, labels: &[String], rows: &[ActivityRow], total: Option<&ActivityRow>) {
+        if rows.is_empty() {
+                return;
+        }
+        let row_name = |row: &ActivityRow| match &row.email {
+                Some(email) => format!("{} <{}>", row.name, email),
+                None => row.name.clone(),
+        };
+        println!("\n{}:", heading);
+        let width = rows.iter().map(|r| row_name(r).len()).max().unwrap_or(0).max(12) + 2;
+        print!("{:<width$}", "");
+        for label in labels {
+                print!("{:>w$}", label, w = label.len().max(6) + 2);
+        }
+        println!();
+        for row in rows.iter().chain(total) {
+                print!("{:<width$}", row_name(row));
+                for (label, count) in labels.iter().zip(row.counts()) {
+                        print!("{:>w$}", count, w = label.len().max(6) + 2);
                 }
+                println!();
         }
 }
 
-fn print_commit(commit: &git2::Commit, date: &DateTime<Utc>) {
-        let hash = commit.id().to_string();
-        let short_hash = &hash[0..7];
-        let summary = commit.summary().unwrap_or("No message");
-        println!("{} | {} | {}", short_hash, date.format("%Y-%m-%d"), summary);
-}
-
-// --- CHARMING (ECharts) GENERATOR ---
-fn generate_pie_chart(title: &str, date: &str, data: Vec<(&str, i32)>) -> Result<()> {
-
-        let mut filename = title.to_string();
-        filename.push_str(".png");
-        // FIX 1: Swap the order. Charming expects (Value, Label), not (Label, Value)
-        let pie_data: Vec<(i32, String)> = data.into_iter()
-                .map(|(label, value)| (value, label.to_string()))
-                .collect();
-
-        // 1. Configure the Title
-        //let title = Title::new()
-        //.text(title)
-        //.subtext(date)
-        //.left("center")
-        //.text_style(TextStyle::new().font_size(25));
-
-        // SERIES 1: The Percentages (Inside the colored box)
-        let inner_series = Pie::new()
-                .name(title)
-                .radius("70%")
-                .data(pie_data.clone()) // Clone data for the first series
-                .item_style(ItemStyle::new().border_radius(10).border_color("#fff").border_width(2))
-                .label(
-                        Label::new()
-                        .show(true)
-                        .position(LabelPosition::Inside)
-                        .formatter("{d}%") // Show only percentage
-                        .color("#fff")
-                        .font_weight("bold")
-                );
-
-        // SERIES 2: The Labels (Outside with pointer lines)
-        let outer_series = Pie::new()
-                .name(title)
-                .radius("70%") // Same radius so it overlaps perfectly
-                .data(pie_data)
-                .item_style(ItemStyle::new().border_radius(10).border_color("#fff").border_width(2))
-                .label(
-                        Label::new()
-                        .show(true)
-                        .position(LabelPosition::Outside)
-                        .formatter("{b}") // Show only the Name (e.g., "Authored")
-                        .color("#000")
-                );
-
-        let chart = Chart::new()
-                .legend(Legend::new().top("bottom"))
-                .title(
-                        Title::new()
-                        .text(title)
-                        .subtext(date)
-                        .left("center"),
-                )
-                .series(inner_series) // Add Series 1
-                .series(outer_series); // Add Series 2
-
-        // Chart dimension 1000x800.
-        let mut renderer = ImageRenderer::new(800, 800).theme(Theme::Shine);
-        // Render the chart as SVG string.
-        renderer.render(&chart).unwrap();
-        // Render the chart as PNG bytes.
-        renderer.render_format(ImageFormat::Png, &chart).unwrap();
-        // Save the chart as SVG file.
-        //renderer.save(&chart, filename).unwrap();
-        renderer.save_format(ImageFormat::Png, &chart, filename).unwrap();
+fn print_diffstats(report: &ContributionReport) {
+        let format_stat = |stat: &DiffStat| format!("{:>6} files {:>+8} {:>8}",
+                stat.files_changed, stat.insertions, format!("-{}", stat.deletions));
+        println!("\nDiffstat:");
+        for (label, stat) in report.category_diffstats() {
+                println!("{:<15}{}", format!("{}:", label), format_stat(&stat));
+        }
+        if report.people.len() > 1 {
+                println!("\nAuthored diffstat per person:");
+                let width = report.people.iter().map(|p| p.name.len()).max().unwrap_or(0) + 2;
+                for person in report.people.iter().chain(Some(&report.people_total())) {
+                        println!("{:<width$}{}", person.name, format_stat(&person.diffstat));
+                }
+        }
+}
 
+/// Label for the commits no target touched, e.g. "Non Linaro" when every
+/// target belongs to the same organization.
+fn untouched_label(config: &ScanConfig) -> String {
+        let mut orgs = config.emails.iter()
+                .map(|email| config.organizations.lookup(email))
+                .chain(config.orgs.iter().map(|org| Some(org.as_str())));
+        match orgs.next() {
+                Some(Some(first)) if orgs.all(|org| org.is_some_and(|o| o.eq_ignore_ascii_case(first))) => {
+                        format!("Non {}", first)
+                }
+                _ => "No interaction".to_string(),
+        }
+}
 
-        Ok(())
+fn print_commit(commit: &MatchedCommit) {
+        println!("{} | {} | {}", commit.short_id(), commit.date.format("%Y-%m-%d"), commit.summary);
 }
@@ -0,0 +1,221 @@
+use anyhow::{Context, Result};
+use git2::Repository;
+use glob::{MatchOptions, Pattern};
+use std::fs;
+use std::path::Path;
+
+/// Label for changed files no MAINTAINERS entry covers.
+pub const UNMAINTAINED: &str = "(unmaintained)";
+
+/// An `F:`/`X:` pattern from a MAINTAINERS entry.
+///
+/// Follows the kernel's rules: a trailing `/` covers everything below the
+/// directory, `*` and `?` do not cross `/`, and a plain path matches that
+/// file or anything below it when it names a directory.
+#[derive(Debug, Clone)]
+struct FilePattern {
+        raw: String,
+        glob: Option<Pattern>,
+}
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+        case_sensitive: true,
+        require_literal_separator: true,
+        require_literal_leading_dot: false,
+};
+
+impl FilePattern {
+        fn new(raw: &str) -> Self {
+                // A directory glob (`mach-*/`) is matched against the path's
+                // directories, so it is compiled without the slash.
+                let glob = if raw.contains(['*', '?', '[']) {
+                        Pattern::new(raw.strip_suffix('/').unwrap_or(raw)).ok()
+                } else {
+                        None
+                };
+                FilePattern { raw: raw.to_string(), glob }
+        }
+
+        fn matches(&self, path: &str) -> bool {
+                match &self.glob {
+                        Some(glob) if self.raw.ends_with('/') => path.match_indices('/')
+                                .any(|(end, _)| glob.matches_with(&path[..end], MATCH_OPTIONS)),
+                        Some(glob) => glob.matches_with(path, MATCH_OPTIONS),
+                        None if self.raw.ends_with('/') => path.starts_with(&self.raw),
+                        None => path == self.raw
+                                || path.strip_prefix(&self.raw).is_some_and(|rest| rest.starts_with('/')),
+                }
+        }
+
+        /// Length of the literal part of the pattern; longer is more specific.
+        fn specificity(&self) -> usize {
+                self.raw.find(['*', '?', '[']).unwrap_or(self.raw.len())
+        }
+}
+
+/// One MAINTAINERS entry: a subsystem title and the files it covers.
+#[derive(Debug, Clone)]
+pub struct Subsystem {
+        pub name: String,
+        files: Vec<FilePattern>,
+        excludes: Vec<FilePattern>,
+}
+
+impl Subsystem {
+        /// Specificity of the best `F:` pattern covering `path`, if any.
+        fn covers(&self, path: &str) -> Option<usize> {
+                if self.excludes.iter().any(|x| x.matches(path)) {
+                        return None;
+                }
+                self.files.iter().filter(|f| f.matches(path)).map(FilePattern::specificity).max()
+        }
+}
+
+/// Parsed MAINTAINERS file as used by Linux, U-Boot and similar projects.
+///
+/// Only `F:` and `X:` lines are used for attribution; `N:` and `K:` regex
+/// entries are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct Maintainers {
+        pub subsystems: Vec<Subsystem>,
+}
+
+impl Maintainers {
+        pub fn load(path: &Path) -> Result<Self> {
+                let content = fs::read_to_string(path)
+                        .with_context(|| format!("Failed to read MAINTAINERS file {:?}", path))?;
+                Ok(Self::parse(&content))
+        }
+
+        /// Reads `MAINTAINERS` from the tree at `HEAD` of the repository.
+        pub fn from_repository(path: &Path) -> Result<Self> {
+                let repo = Repository::open(path)
+                        .with_context(|| format!("Failed to open git repository at {:?}", path))?;
+                let tree = repo.head().and_then(|head| head.peel_to_tree())
+                        .context("Failed to find HEAD")?;
+                let entry = tree.get_path(Path::new("MAINTAINERS"))
+                        .context("No MAINTAINERS file at HEAD")?;
+                let blob = entry.to_object(&repo).and_then(|obj| obj.peel_to_blob())
+                        .context("Failed to read MAINTAINERS blob")?;
+                Ok(Self::parse(&String::from_utf8_lossy(blob.content())))
+        }
+
+        pub fn parse(content: &str) -> Self {
+                let mut subsystems = Vec::new();
+                let mut current: Option<Subsystem> = None;
+                for line in content.lines() {
+                        let tag = line.split_once(':')
+                                .filter(|(tag, _)| tag.len() == 1 && tag.chars().all(|c| c.is_ascii_uppercase()));
+                        match tag {
+                                Some((tag, value)) => {
+                                        let Some(section) = current.as_mut() else { continue };
+                                        let value = value.trim();
+                                        match tag {
+                                                "F" => section.files.push(FilePattern::new(value)),
+                                                "X" => section.excludes.push(FilePattern::new(value)),
+                                                _ => {}
+                                        }
+                                }
+                                None if line.trim().is_empty() => {
+                                        subsystems.extend(current.take());
+                                }
+                                None => {
+                                        // A new title ends any section that had no blank line after it.
+                                        subsystems.extend(current.take());
+                                        current = Some(Subsystem {
+                                                name: line.trim().to_string(),
+                                                files: Vec::new(),
+                                                excludes: Vec::new(),
+                                        });
+                                }
+                        }
+                }
+                subsystems.extend(current);
+                subsystems.retain(|s| !s.files.is_empty());
+                Maintainers { subsystems }
+        }
+
+        /// Subsystems responsible for `paths`. Each file goes to the entries
+        /// with its most specific matching pattern, so catch-all entries such
+        /// as "THE REST" only get files nobody else covers.
+        pub fn attribute<'a>(&self, paths: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+                let mut names: Vec<String> = Vec::new();
+                for path in paths {
+                        let matches: Vec<(usize, &Subsystem)> = self.subsystems.iter()
+                                .filter_map(|s| s.covers(path).map(|spec| (spec, s)))
+                                .collect();
+                        let best = matches.iter().map(|(spec, _)| *spec).max();
+                        let owners: Vec<&str> = match best {
+                                Some(best) => matches.iter()
+                                        .filter(|(spec, _)| *spec == best)
+                                        .map(|(_, s)| s.name.as_str())
+                                        .collect(),
+                                None => vec![UNMAINTAINED],
+                        };
+                        for owner in owners {
+                                if !names.iter().any(|n| n == owner) {
+                                        names.push(owner.to_string());
+                                }
+                        }
+                }
+                names
+        }
+}
+
+#[cfg(test)]
+mod tests {
+        use super::*;
+
+        const MAINTAINERS: &str = "\
+ARM PORT
+M:\tJane <jane@x.org>
+F:\tarch/arm/
+X:\tarch/arm/boot/dts/
+
+ARM DTS
+F:\tarch/arm/boot/dts/
+
+EFI
+F:\tlib/efi_loader/*.c
+THE REST
+F:\t*
+F:\t*/
+";
+
+        #[test]
+        fn parses_entries_with_file_patterns() {
+                let maintainers = Maintainers::parse(MAINTAINERS);
+                let names: Vec<&str> = maintainers.subsystems.iter().map(|s| s.name.as_str()).collect();
+                assert_eq!(names, ["ARM PORT", "ARM DTS", "EFI", "THE REST"]);
+        }
+
+        #[test]
+        fn most_specific_pattern_wins() {
+                let maintainers = Maintainers::parse(MAINTAINERS);
+                assert_eq!(maintainers.attribute(["arch/arm/kernel/setup.c"]), ["ARM PORT"]);
+                assert_eq!(maintainers.attribute(["lib/efi_loader/efi_boot.c"]), ["EFI"]);
+                assert_eq!(maintainers.attribute(["Makefile"]), ["THE REST"]);
+                assert_eq!(maintainers.attribute(["drivers/foo.c"]), ["THE REST"]);
+        }
+
+        #[test]
+        fn directory_globs_cover_files_below() {
+                let maintainers = Maintainers::parse("IMX\nF:\tarch/arm/mach-*/\n\nFOO\nF:\tdrivers/*/foo/\n");
+                assert_eq!(maintainers.attribute(["arch/arm/mach-imx/board.c"]), ["IMX"]);
+                assert_eq!(maintainers.attribute(["arch/arm/mach-imx/sub/pm.c"]), ["IMX"]);
+                assert_eq!(maintainers.attribute(["drivers/net/foo/main.c"]), ["FOO"]);
+                assert_eq!(maintainers.attribute(["arch/arm/mach-imx"]), [UNMAINTAINED]);
+        }
+
+        #[test]
+        fn excluded_files_go_elsewhere() {
+                let maintainers = Maintainers::parse(MAINTAINERS);
+                assert_eq!(maintainers.attribute(["arch/arm/boot/dts/board.dts"]), ["ARM DTS"]);
+        }
+
+        #[test]
+        fn globs_do_not_cross_directories() {
+                let maintainers = Maintainers::parse("EFI\nF:\tlib/efi_loader/*.c\n");
+                assert_eq!(maintainers.attribute(["lib/efi_loader/sub/x.c"]), [UNMAINTAINED]);
+        }
+}
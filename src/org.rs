@@ -0,0 +1,96 @@
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Bucket for addresses the organization map does not know.
+pub const UNAFFILIATED: &str = "Unaffiliated";
+
+/// Maps email addresses to the organization they belong to.
+///
+/// The file format is one `pattern = Organization` per line, where the
+/// pattern is either a full address (`jane@gmail.com`) or a domain
+/// (`linaro.org`, which also covers subdomains such as `st.linaro.org`).
+/// Explicit addresses win over domains. Blank lines and `#` comments are
+/// skipped.
+#[derive(Debug, Clone, Default)]
+pub struct Organizations {
+        addresses: HashMap<String, String>,
+        domains: HashMap<String, String>,
+}
+
+impl Organizations {
+        pub fn load(path: &Path) -> Result<Self> {
+                let content = fs::read_to_string(path)
+                        .with_context(|| format!("Failed to read organization file {:?}", path))?;
+                Self::parse(&content).with_context(|| format!("In organization file {:?}", path))
+        }
+
+        pub fn parse(content: &str) -> Result<Self> {
+                let mut orgs = Organizations::default();
+                for (lineno, line) in content.lines().enumerate() {
+                        let line = line.trim();
+                        if line.is_empty() || line.starts_with('#') {
+                                continue;
+                        }
+                        let Some((pattern, name)) = line.split_once('=') else {
+                                bail!("line {}: expected `pattern = Organization`", lineno + 1);
+                        };
+                        let (pattern, name) = (pattern.trim().to_lowercase(), name.trim());
+                        if pattern.is_empty() || name.is_empty() {
+                                bail!("line {}: empty pattern or organization", lineno + 1);
+                        }
+                        orgs.insert(&pattern, name);
+                }
+                Ok(orgs)
+        }
+
+        pub fn insert(&mut self, pattern: &str, name: &str) {
+                let pattern = pattern.to_lowercase();
+                if pattern.contains('@') && !pattern.starts_with('@') {
+                        self.addresses.insert(pattern, name.to_string());
+                } else {
+                        self.domains.insert(pattern.trim_start_matches('@').to_string(), name.to_string());
+                }
+        }
+
+        pub fn is_empty(&self) -> bool {
+                self.addresses.is_empty() && self.domains.is_empty()
+        }
+
+        /// The organization `email` belongs to, if the map knows it.
+        pub fn lookup(&self, email: &str) -> Option<&str> {
+                let email = email.to_lowercase();
+                if let Some(name) = self.addresses.get(&email) {
+                        return Some(name);
+                }
+                let (_, mut domain) = email.rsplit_once('@')?;
+                loop {
+                        if let Some(name) = self.domains.get(domain) {
+                                return Some(name);
+                        }
+                        domain = domain.split_once('.')?.1;
+                }
+        }
+}
+
+#[cfg(test)]
+mod tests {
+        use super::*;
+
+        #[test]
+        fn lookup_walks_up_subdomains_and_prefers_addresses() {
+                let orgs = Organizations::parse("# map\nlinaro.org = Linaro\n@arm.com = Arm\njane@gmail.com = Arm\n").unwrap();
+                assert_eq!(orgs.lookup("bob@st.linaro.org"), Some("Linaro"));
+                assert_eq!(orgs.lookup("Alice@ARM.com"), Some("Arm"));
+                assert_eq!(orgs.lookup("jane@gmail.com"), Some("Arm"));
+                assert_eq!(orgs.lookup("joe@gmail.com"), None);
+                assert_eq!(orgs.lookup("bob@notlinaro.org"), None);
+        }
+
+        #[test]
+        fn rejects_malformed_lines() {
+                assert!(Organizations::parse("linaro.org Linaro\n").is_err());
+                assert!(Organizations::parse("= Linaro\n").is_err());
+        }
+}
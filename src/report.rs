@@ -0,0 +1,496 @@
+use chrono::{DateTime, Utc};
+use git2::Oid;
+
+use crate::diffstat::DiffStat;
+use crate::identity::Identity;
+use crate::trailers::{Trailer, TrailerKind};
+
+/// The part a target identity played in a commit.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Role {
+        Author,
+        /// Authored a merge commit while merges are counted separately.
+        Merger,
+        /// Committed (applied) someone else's commit.
+        Committer,
+        /// Named in (or, for authored-only kinds, carried) a trailer with this key.
+        Trailer(String),
+}
+
+impl Role {
+        /// Name for exports, e.g. `author` or `reviewer`. Trailer roles take
+        /// the role name of their kind in `kinds`.
+        pub fn name(&self, kinds: &[TrailerCount]) -> String {
+                match self {
+                        Role::Author => "author".to_string(),
+                        Role::Merger => "merger".to_string(),
+                        Role::Committer => "committer".to_string(),
+                        Role::Trailer(key) => kinds.iter()
+                                .find(|t| t.kind.key == *key)
+                                .map_or_else(|| key.to_lowercase(), |t| t.kind.role.clone()),
+                }
+        }
+}
+
+/// A scanned commit that at least one target identity was involved in.
+#[derive(Debug, Clone)]
+pub struct MatchedCommit {
+        pub id: Oid,
+        /// Name of the repository's row in [`ContributionReport::repositories`].
+        pub repository: String,
+        pub date: DateTime<Utc>,
+        pub author: String,
+        pub author_email: String,
+        pub summary: String,
+        pub roles: Vec<Role>,
+        /// The trailer lines that credited a target.
+        pub trailers: Vec<Trailer>,
+        /// Against the first parent; `None` unless diffstats were requested,
+        /// and always for merges.
+        pub diffstat: Option<DiffStat>,
+        /// MAINTAINERS subsystems of the files touched.
+        pub subsystems: Vec<String>,
+}
+
+impl MatchedCommit {
+        pub fn short_id(&self) -> String {
+                self.id.to_string()[0..7].to_string()
+        }
+
+        pub fn has_role(&self, role: &Role) -> bool {
+                self.roles.contains(role)
+        }
+}
+
+/// A counted trailer (e.g. `Reported-by: syzbot`) with no email to match.
+#[derive(Debug, Clone)]
+pub struct UnparsedTrailer {
+        pub commit: Oid,
+        pub trailer: Trailer,
+}
+
+/// A review reply from a target found in a mailing-list archive.
+#[derive(Debug, Clone)]
+pub struct ListReply {
+        pub message_id: String,
+        pub date: Option<DateTime<Utc>>,
+        pub from: Identity,
+        pub subject: String,
+        /// Keys of the review tags it gave, e.g. `Reviewed-by`.
+        pub tags: Vec<String>,
+        /// Whether it comments inline on a quoted patch.
+        pub comments: bool,
+}
+
+/// The targets' review activity on the mailing lists, counted apart from
+/// the trailers that made it into git.
+#[derive(Debug, Clone, Default)]
+pub struct ListActivity {
+        /// Messages read, after dropping duplicates and those out of range.
+        pub messages: usize,
+        /// Patches the targets posted, not counting cover letters.
+        pub patches: usize,
+        /// Replies giving each trailer kind, aligned with
+        /// `ContributionReport::trailers`; only identity kinds are counted.
+        pub tags: Vec<usize>,
+        /// Replies commenting inline on a quoted patch.
+        pub comments: usize,
+        pub replies: Vec<ListReply>,
+}
+
+/// How a downstream patch was recognized upstream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpstreamMatch {
+        /// Same change, by `git patch-id`.
+        PatchId,
+        /// Same subject, e.g. after the patch was reworked for upstream.
+        Subject,
+}
+
+impl UpstreamMatch {
+        pub fn name(&self) -> &'static str {
+                match self {
+                        UpstreamMatch::PatchId => "patch-id",
+                        UpstreamMatch::Subject => "subject",
+                }
+        }
+}
+
+/// The upstream commit a downstream patch landed as.
+#[derive(Debug, Clone)]
+pub struct Landing {
+        pub id: Oid,
+        pub matched_by: UpstreamMatch,
+        /// Earliest tag in the upstream range containing the commit; `None`
+        /// if no tag there does yet.
+        pub release: Option<String>,
+}
+
+/// One of the targets' downstream patches and whether it is upstream.
+#[derive(Debug, Clone)]
+pub struct UpstreamPatch {
+        pub id: Oid,
+        /// Name of the repository's row in [`ContributionReport::repositories`].
+        pub repository: String,
+        /// Author date, which a rebase of the vendor tree keeps.
+        pub date: DateTime<Utc>,
+        pub author_email: String,
+        pub summary: String,
+        /// `None` while the patch is pending.
+        pub landed: Option<Landing>,
+}
+
+impl UpstreamPatch {
+        pub fn short_id(&self) -> String {
+                self.id.to_string()[0..7].to_string()
+        }
+}
+
+/// How often the targets appeared in one kind of trailer.
+#[derive(Debug, Clone)]
+pub struct TrailerCount {
+        pub kind: TrailerKind,
+        pub count: usize,
+}
+
+/// Authored and trailer activity of one person or organization.
+#[derive(Debug, Clone)]
+pub struct ActivityRow {
+        pub name: String,
+        /// Mailmapped email for per-person rows; `None` for organizations.
+        pub email: Option<String>,
+        pub authored: usize,
+        /// Merges authored; `Some` only when merges are counted separately.
+        pub merged: Option<usize>,
+        /// Commits applied for someone else.
+        pub committed: usize,
+        /// Counts per trailer kind, aligned with `ContributionReport::trailers`.
+        pub trailers: Vec<usize>,
+        /// Summed over the commits counted as authored.
+        pub diffstat: DiffStat,
+        /// Matched commits per MAINTAINERS subsystem; filled for people only.
+        pub subsystems: Vec<(String, usize)>,
+}
+
+impl ActivityRow {
+        pub fn new(name: &str, email: Option<&str>, kinds: usize) -> Self {
+                ActivityRow {
+                        name: name.to_string(),
+                        email: email.map(str::to_string),
+                        authored: 0,
+                        merged: None,
+                        committed: 0,
+                        trailers: vec![0; kinds],
+                        diffstat: DiffStat::default(),
+                        subsystems: Vec::new(),
+                }
+        }
+
+        /// Adds the counts and diffstat of `other` to this row.
+        pub fn add(&mut self, other: &ActivityRow) {
+                self.authored += other.authored;
+                if let (Some(sum), Some(merged)) = (self.merged.as_mut(), other.merged) {
+                        *sum += merged;
+                }
+                self.committed += other.committed;
+                for (sum, count) in self.trailers.iter_mut().zip(&other.trailers) {
+                        *sum += count;
+                }
+                self.diffstat += other.diffstat;
+        }
+
+        pub(crate) fn count_subsystem(&mut self, name: &str) {
+                match self.subsystems.iter_mut().find(|(n, _)| n == name) {
+                        Some((_, count)) => *count += 1,
+                        None => self.subsystems.push((name.to_string(), 1)),
+                }
+        }
+
+        /// Authored, (merged,) and committed counts followed by each trailer
+        /// count, in the order of [`ContributionReport::activity_labels`].
+        pub fn counts(&self) -> Vec<usize> {
+                std::iter::once(self.authored)
+                        .chain(self.merged)
+                        .chain(std::iter::once(self.committed))
+                        .chain(self.trailers.iter().copied())
+                        .collect()
+        }
+
+        pub fn total(&self) -> usize {
+                self.counts().iter().sum()
+        }
+}
+
+/// Result of a [`scan`](crate::scan).
+#[derive(Debug, Clone, Default)]
+pub struct ContributionReport {
+        pub total_scanned: usize,
+        pub authored: usize,
+        /// Merges authored by the targets; `Some` only when merges are
+        /// counted as their own category instead of as authored commits.
+        pub merged: Option<usize>,
+        /// Commits the targets committed but did not author, i.e. patches
+        /// they applied as maintainers.
+        pub committed: usize,
+        /// One entry per configured trailer kind, in table order.
+        pub trailers: Vec<TrailerCount>,
+        pub commits: Vec<MatchedCommit>,
+        pub unparsed_trailers: Vec<UnparsedTrailer>,
+        /// Activity of everyone in the scanned history, grouped by
+        /// organization; empty unless an organization map was configured.
+        pub organizations: Vec<ActivityRow>,
+        /// One row per mailmapped target identity that was matched.
+        pub people: Vec<ActivityRow>,
+        /// The targets' activity per MAINTAINERS subsystem; empty unless a
+        /// MAINTAINERS file was configured. A commit touching several
+        /// subsystems counts in each.
+        pub subsystems: Vec<ActivityRow>,
+        /// The targets' activity per time bucket, oldest first and without
+        /// gaps; empty unless bucketing was requested.
+        pub timeline: Vec<ActivityRow>,
+        /// The targets' activity per scanned repository, in scan order.
+        pub repositories: Vec<ActivityRow>,
+        /// `Some` when mailing-list archives were read.
+        pub mailing_list: Option<ListActivity>,
+        /// The targets' downstream patches, newest first per repository;
+        /// `Some` when upstream ranges were given.
+        pub upstream: Option<Vec<UpstreamPatch>>,
+}
+
+impl ContributionReport {
+        pub fn new(kinds: &[TrailerKind]) -> Self {
+                ContributionReport {
+                        trailers: kinds.iter()
+                                .map(|kind| TrailerCount { kind: kind.clone(), count: 0 })
+                                .collect(),
+                        ..Default::default()
+                }
+        }
+
+        pub(crate) fn new_row(&self, name: &str, email: Option<&str>) -> ActivityRow {
+                let mut row = ActivityRow::new(name, email, self.trailers.len());
+                row.merged = self.merged.map(|_| 0);
+                row
+        }
+
+        pub(crate) fn org_entry(&mut self, name: &str) -> &mut ActivityRow {
+                let index = match self.organizations.iter().position(|o| o.name == name) {
+                        Some(index) => index,
+                        None => {
+                                self.organizations.push(self.new_row(name, None));
+                                self.organizations.len() - 1
+                        }
+                };
+                &mut self.organizations[index]
+        }
+
+        pub(crate) fn subsystem_entry(&mut self, name: &str) -> &mut ActivityRow {
+                let index = match self.subsystems.iter().position(|s| s.name == name) {
+                        Some(index) => index,
+                        None => {
+                                self.subsystems.push(self.new_row(name, None));
+                                self.subsystems.len() - 1
+                        }
+                };
+                &mut self.subsystems[index]
+        }
+
+        pub(crate) fn repository_entry(&mut self, name: &str) -> &mut ActivityRow {
+                let index = match self.repositories.iter().position(|r| r.name == name) {
+                        Some(index) => index,
+                        None => {
+                                self.repositories.push(self.new_row(name, None));
+                                self.repositories.len() - 1
+                        }
+                };
+                &mut self.repositories[index]
+        }
+
+        pub(crate) fn person_entry(&mut self, identity: &Identity) -> &mut ActivityRow {
+                let email = identity.email.to_lowercase();
+                let index = match self.people.iter().position(|p| p.email.as_deref() == Some(email.as_str())) {
+                        Some(index) => index,
+                        None => {
+                                let name = identity.name.as_deref().unwrap_or(&email);
+                                let row = self.new_row(name, Some(&email));
+                                self.people.push(row);
+                                self.people.len() - 1
+                        }
+                };
+                &mut self.people[index]
+        }
+
+        /// Sum of all per-person rows.
+        pub fn people_total(&self) -> ActivityRow {
+                self.total_of(&self.people)
+        }
+
+        /// Sum of all per-repository rows, i.e. the combined activity.
+        pub fn repositories_total(&self) -> ActivityRow {
+                self.total_of(&self.repositories)
+        }
+
+        fn total_of(&self, rows: &[ActivityRow]) -> ActivityRow {
+                let mut total = self.new_row("Total", None);
+                for row in rows {
+                        total.add(row);
+                }
+                total
+        }
+
+        /// The role behind each activity category, in the order of
+        /// [`ContributionReport::activity_labels`].
+        fn activity_roles(&self) -> Vec<Role> {
+                let mut roles = vec![Role::Author];
+                if self.merged.is_some() {
+                        roles.push(Role::Merger);
+                }
+                roles.push(Role::Committer);
+                roles.extend(self.trailers.iter().map(|t| Role::Trailer(t.kind.key.clone())));
+                roles
+        }
+
+        /// Summed diffstat of the matched commits in each activity category,
+        /// in the order of [`ContributionReport::activity_labels`]. A commit
+        /// counts once per category it appears in.
+        pub fn category_diffstats(&self) -> Vec<(String, DiffStat)> {
+                self.activity_labels().into_iter().zip(self.activity_roles())
+                        .map(|(label, role)| {
+                                let mut sum = DiffStat::default();
+                                for commit in self.commits.iter().filter(|c| c.has_role(&role)) {
+                                        sum += commit.diffstat.unwrap_or_default();
+                                }
+                                (label, sum)
+                        })
+                        .collect()
+        }
+
+        /// Labels matching [`ActivityRow::counts`].
+        pub fn activity_labels(&self) -> Vec<String> {
+                self.activity().into_iter().map(|(label, _)| label).collect()
+        }
+
+        /// Count for the trailer kind with `key`, or 0 if it is not in the table.
+        pub fn trailer_count(&self, key: &str) -> usize {
+                self.trailers.iter()
+                        .find(|t| t.kind.key.eq_ignore_ascii_case(key))
+                        .map_or(0, |t| t.count)
+        }
+
+        /// `(label, count)` for authored and committed commits followed by
+        /// every trailer kind.
+        pub fn activity(&self) -> Vec<(String, usize)> {
+                let mut rows = vec![("Authored".to_string(), self.authored)];
+                if let Some(merged) = self.merged {
+                        rows.push(("Merged".to_string(), merged));
+                }
+                rows.push(("Committed".to_string(), self.committed));
+                rows.extend(self.trailers.iter().map(|t| (t.kind.label.clone(), t.count)));
+                rows
+        }
+
+        /// Number of events across all categories; a commit with two
+        /// matching Reviewed-by trailers counts twice.
+        pub fn total_activity(&self) -> usize {
+                self.activity().iter().map(|(_, count)| count).sum()
+        }
+
+        /// Upstream patches that have landed and that are pending.
+        pub fn upstream_counts(&self) -> (usize, usize) {
+                let patches = self.upstream.as_deref().unwrap_or_default();
+                let landed = patches.iter().filter(|patch| patch.landed.is_some()).count();
+                (landed, patches.len() - landed)
+        }
+
+        /// Scanned commits the targets took part in, each counted once.
+        pub fn involved(&self) -> usize {
+                self.commits.len()
+        }
+
+        /// Scanned commits that the targets did not interact with at all.
+        pub fn no_interaction(&self) -> usize {
+                self.total_scanned.saturating_sub(self.involved())
+        }
+
+        /// `(label, commits)` with every involved commit counted once, under
+        /// the first of its roles in the order of
+        /// [`ContributionReport::activity_labels`]: a commit that was both
+        /// authored and reviewed by the targets counts as authored. Unlike
+        /// [`ContributionReport::activity`], the counts add up to
+        /// [`ContributionReport::involved`].
+        pub fn involvement(&self) -> Vec<(String, usize)> {
+                let roles = self.activity_roles();
+                let mut counts = vec![0; roles.len()];
+                for commit in &self.commits {
+                        if let Some(index) = roles.iter().position(|role| commit.has_role(role)) {
+                                counts[index] += 1;
+                        }
+                }
+                self.activity_labels().into_iter().zip(counts).collect()
+        }
+}
+
+#[cfg(test)]
+mod tests {
+        use super::*;
+
+        fn commit(roles: Vec<Role>) -> MatchedCommit {
+                MatchedCommit {
+                        id: Oid::zero(),
+                        repository: "repo".to_string(),
+                        date: DateTime::default(),
+                        author: "Bob".to_string(),
+                        author_email: "bob@example.org".to_string(),
+                        summary: "change".to_string(),
+                        roles,
+                        trailers: Vec::new(),
+                        diffstat: None,
+                        subsystems: Vec::new(),
+                }
+        }
+
+        /// Ten scanned commits: one authored and also reviewed by the
+        /// targets, one carrying two of their Reviewed-by trailers and one
+        /// they committed.
+        fn report() -> ContributionReport {
+                let reviewed = Role::Trailer("Reviewed-by".to_string());
+                let mut report = ContributionReport::new(&TrailerKind::defaults());
+                report.total_scanned = 10;
+                report.authored = 1;
+                report.committed = 1;
+                report.trailers[0].count = 3;
+                report.commits = vec![
+                        commit(vec![Role::Author, reviewed.clone()]),
+                        commit(vec![reviewed.clone(), reviewed]),
+                        commit(vec![Role::Committer]),
+                ];
+                report
+        }
+
+        fn count(rows: &[(String, usize)], label: &str) -> usize {
+                rows.iter().find(|(l, _)| l == label).map_or(0, |(_, count)| *count)
+        }
+
+        #[test]
+        fn involved_counts_each_commit_once() {
+                let report = report();
+                assert_eq!(report.total_activity(), 5);
+                assert_eq!(report.involved(), 3);
+                assert_eq!(report.no_interaction(), 7);
+        }
+
+        #[test]
+        fn involvement_files_commits_under_their_first_role() {
+                let involvement = report().involvement();
+                assert_eq!(count(&involvement, "Authored"), 1);
+                assert_eq!(count(&involvement, "Committed"), 1);
+                assert_eq!(count(&involvement, "Reviewed"), 1);
+        }
+
+        #[test]
+        fn involvement_and_untouched_add_up_to_total_scanned() {
+                let report = report();
+                let involved: usize = report.involvement().iter().map(|(_, count)| count).sum();
+                assert_eq!(involved + report.no_interaction(), report.total_scanned);
+        }
+}
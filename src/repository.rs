@@ -0,0 +1,93 @@
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Repositories at or below `root`: `root` itself if it is a repository,
+/// otherwise every repository found by searching it recursively, sorted
+/// by path. The search does not descend into repositories it found, so
+/// submodules and nested checkouts are not scanned twice.
+pub fn find_repositories(root: &Path) -> Result<Vec<PathBuf>> {
+        let mut found = Vec::new();
+        if is_repository(root) {
+                found.push(root.to_path_buf());
+        } else {
+                search(root, &mut found)?;
+        }
+        if found.is_empty() {
+                bail!("No git repository at or below {:?}", root);
+        }
+        found.sort();
+        Ok(found)
+}
+
+fn search(dir: &Path, found: &mut Vec<PathBuf>) -> Result<()> {
+        let entries = fs::read_dir(dir).with_context(|| format!("Failed to read directory {:?}", dir))?;
+        for entry in entries {
+                let entry = entry?;
+                // Symlinks are not followed, so loops cannot occur.
+                if !entry.file_type()?.is_dir() || entry.file_name() == ".git" {
+                        continue;
+                }
+                let path = entry.path();
+                if is_repository(&path) {
+                        found.push(path);
+                } else {
+                        search(&path, found)?;
+                }
+        }
+        Ok(())
+}
+
+/// A working tree with a `.git` directory or file, or a bare repository.
+fn is_repository(path: &Path) -> bool {
+        path.join(".git").exists()
+                || (path.join("HEAD").is_file() && path.join("objects").is_dir() && path.join("refs").is_dir())
+}
+
+/// Short, unique display names for `paths`: the directory name for a
+/// single repository, otherwise each path relative to the deepest common
+/// ancestor, e.g. `linux` and `tools/qemu`.
+pub fn repository_names(paths: &[PathBuf]) -> Result<Vec<String>> {
+        let paths = canonicalize(paths)?;
+        if paths.len() < 2 {
+                return Ok(paths.iter().map(|path| name_of(path)).collect());
+        }
+        let common = common_ancestor(&paths);
+        Ok(paths.iter()
+                .map(|path| match path.strip_prefix(&common) {
+                        Ok(relative) if !relative.as_os_str().is_empty() => relative.display().to_string(),
+                        _ => name_of(path),
+                })
+                .collect())
+}
+
+/// A name for everything in `paths` together: the repository's name for a
+/// single one, the name of the directory holding them all, or just their
+/// number when they share no directory but the root.
+pub fn combined_name(paths: &[PathBuf]) -> Result<String> {
+        let paths = canonicalize(paths)?;
+        if let [path] = paths.as_slice() {
+                return Ok(name_of(path));
+        }
+        Ok(common_ancestor(&paths).file_name()
+                .map_or_else(|| format!("{} repositories", paths.len()), |name| name.to_string_lossy().into_owned()))
+}
+
+fn canonicalize(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+        paths.iter()
+                .map(|path| path.canonicalize().with_context(|| format!("Failed to resolve {:?}", path)))
+                .collect()
+}
+
+fn name_of(path: &Path) -> String {
+        path.file_name()
+                .map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned())
+}
+
+fn common_ancestor(paths: &[PathBuf]) -> PathBuf {
+        let mut common = paths.first().cloned().unwrap_or_default();
+        while !paths.iter().all(|path| path.starts_with(&common)) {
+                common.pop();
+        }
+        common
+}
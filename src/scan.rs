@@ -0,0 +1,575 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use git2::{Repository, Revwalk, Sort, Time};
+use glob::Pattern;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::diffstat::{changed_paths, commit_diffstat, touches_paths};
+use crate::identity::{EmailMatcher, Identity, IdentityResolver};
+use crate::mail::MailScanner;
+use crate::maintainers::Maintainers;
+use crate::org::{Organizations, UNAFFILIATED};
+use crate::repository::repository_names;
+use crate::report::{ActivityRow, ContributionReport, ListActivity, MatchedCommit, Role, UnparsedTrailer};
+use crate::timeline::{Bucket, fill_gaps};
+use crate::trailers::{TrailerKind, analyze_trailers, parse_trailers};
+use crate::upstream::track_upstream;
+
+/// Which commit timestamp `since`/`until` filter on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DateField {
+        /// When the commit was created or last rewritten; what git log sorts by.
+        #[default]
+        Committer,
+        /// When the change was originally written; survives rebases and
+        /// cherry-picks.
+        Author,
+}
+
+/// What to do with merge commits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeMode {
+        /// Scan merges like any other commit.
+        #[default]
+        Include,
+        /// Skip merges entirely; they are not part of `total_scanned` either.
+        Exclude,
+        /// Credit merges to a "Merged" category instead of "Authored", so
+        /// integration work is visible on its own.
+        Separate,
+}
+
+/// How many consecutive commits older than `since` the walk tolerates
+/// before stopping, like git's own `--since` handling. Commit dates are
+/// not monotonic, so a single old commit does not end the walk.
+const SINCE_SLOP: usize = 5;
+
+/// How far an author date may run ahead of its committer date and still
+/// be found when filtering on author dates. Rebases and cherry-picks keep
+/// the author date and move the committer date forward, so only `--date`
+/// overrides and clock skew put it ahead; beyond this window such commits
+/// are missed in exchange for pruning the walk.
+const AUTHOR_DATE_SKEW: Duration = Duration::days(7);
+
+/// What to scan and whose contributions to look for.
+#[derive(Debug, Clone)]
+pub struct ScanConfig {
+        /// Repositories to scan; their activity is summed into one report
+        /// with a row per repository.
+        pub paths: Vec<PathBuf>,
+        pub emails: Vec<String>,
+        pub since: Option<DateTime<Utc>>,
+        /// Exclusive upper bound on the commit date.
+        pub until: Option<DateTime<Utc>>,
+        pub date_field: DateField,
+        /// Revisions and ranges to walk (`v1.0..v2.0`, `main`, `^old`);
+        /// `HEAD` when both this and `ref_globs` are empty.
+        pub revisions: Vec<String>,
+        /// Ref globs whose tips are walked, e.g. `refs/heads/*` or
+        /// `refs/remotes/origin/release-*`. Commits reachable from several
+        /// refs are still counted once.
+        pub ref_globs: Vec<String>,
+        pub merges: MergeMode,
+        /// Follow only the first parent of merges, i.e. the mainline.
+        pub first_parent: bool,
+        /// Only count a commit as committed by a target when it also
+        /// carries the committer's `Signed-off-by`.
+        pub require_signoff: bool,
+        pub partial: bool,
+        pub trailer_kinds: Vec<TrailerKind>,
+        /// Email to organization map; enables the per-organization breakdown.
+        pub organizations: Organizations,
+        /// Organizations whose members all count as targets.
+        pub orgs: Vec<String>,
+        /// Resolve identities through the repository's `.mailmap`.
+        pub use_mailmap: bool,
+        /// Extra mailmap applied on top of the repository's.
+        pub mailmap_file: Option<PathBuf>,
+        /// Only scan commits touching these pathspecs; empty means the whole
+        /// tree. Filtered-out commits are not part of `total_scanned`.
+        pub path_filters: Vec<String>,
+        /// Attribute matched commits to subsystems by the files they touch.
+        pub maintainers: Option<Maintainers>,
+        /// Compute insertions/deletions/files for every matched commit.
+        pub diffstat: bool,
+        /// Paths left out of diffstats, e.g. generated or vendored code.
+        pub exclude_paths: Vec<Pattern>,
+        /// Group the targets' activity into a time series by `date_field`.
+        pub bucket: Option<Bucket>,
+        /// mbox files and maildirs to read list reviews from.
+        pub mail_archives: Vec<PathBuf>,
+        /// public-inbox v2 archives, or single epoch repositories, to read
+        /// list activity from.
+        pub public_inboxes: Vec<PathBuf>,
+        /// Revisions and ranges of an upstream tree, e.g. `v6.1..v6.8`. When
+        /// set, the non-merge commits the targets authored in the scanned
+        /// (downstream) history are looked for upstream.
+        pub upstream_ranges: Vec<String>,
+}
+
+impl ScanConfig {
+        pub fn new(path: impl Into<PathBuf>) -> Self {
+                ScanConfig {
+                        paths: vec![path.into()],
+                        emails: Vec::new(),
+                        since: None,
+                        until: None,
+                        date_field: DateField::Committer,
+                        revisions: Vec::new(),
+                        ref_globs: Vec::new(),
+                        merges: MergeMode::Include,
+                        first_parent: false,
+                        require_signoff: false,
+                        partial: false,
+                        trailer_kinds: TrailerKind::defaults(),
+                        organizations: Organizations::default(),
+                        orgs: Vec::new(),
+                        use_mailmap: true,
+                        mailmap_file: None,
+                        path_filters: Vec::new(),
+                        maintainers: None,
+                        diffstat: false,
+                        exclude_paths: Vec::new(),
+                        bucket: None,
+                        mail_archives: Vec::new(),
+                        public_inboxes: Vec::new(),
+                        upstream_ranges: Vec::new(),
+                }
+        }
+
+        /// Human-readable description of what was walked, used for chart
+        /// subtitles, e.g. `v2024.01..v2024.04` or `2024-01-01 -- Today`.
+        pub fn range_label(&self) -> String {
+                let dates = match (self.since, self.until) {
+                        (None, None) => None,
+                        (since, until) => Some(format!("{} -- {}",
+                                since.map_or("Start".to_string(), |d| d.format("%Y-%m-%d").to_string()),
+                                until.map_or("Today".to_string(), |d| (d - Duration::seconds(1)).format("%Y-%m-%d").to_string()))),
+                };
+                let tips: Vec<&str> = self.revisions.iter().chain(&self.ref_globs).map(String::as_str).collect();
+                let revisions = (!tips.is_empty()).then(|| tips.join(" "));
+                match (revisions, dates) {
+                        (Some(revs), Some(dates)) => format!("{}, {}", revs, dates),
+                        (Some(revs), None) => revs,
+                        (None, Some(dates)) => dates,
+                        (None, None) => "Overall".to_string(),
+                }
+        }
+}
+
+/// Parses a `YYYY-MM-DD` date as midnight UTC.
+pub fn parse_date(date_str: &str) -> Result<DateTime<Utc>> {
+        let naive_date = NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
+                .context("Invalid date format. Please use YYYY-MM-DD")?;
+        Ok(Utc.from_utc_datetime(&naive_date.and_hms_opt(0, 0, 0).unwrap()))
+}
+
+/// Parses a `YYYY-MM-DD` date as the end of that day, for use as an
+/// exclusive `until` bound that still includes the whole day.
+pub fn parse_end_date(date_str: &str) -> Result<DateTime<Utc>> {
+        Ok(parse_date(date_str)? + Duration::days(1))
+}
+
+fn to_datetime(time: Time) -> DateTime<Utc> {
+        DateTime::from_timestamp(time.seconds(), 0).unwrap_or_default()
+}
+
+fn push_revisions(repo: &Repository, revwalk: &mut Revwalk, config: &ScanConfig) -> Result<()> {
+        if config.revisions.is_empty() && config.ref_globs.is_empty() {
+                revwalk.push_head().context("Failed to find HEAD")?;
+                return Ok(());
+        }
+        for glob in &config.ref_globs {
+                revwalk.push_glob(glob)
+                        .with_context(|| format!("Failed to push refs matching {:?}", glob))?;
+        }
+        for rev in &config.revisions {
+                push_revision(repo, revwalk, rev)?;
+        }
+        Ok(())
+}
+
+/// Pushes one revision (`main`), range (`v1.0..v2.0`) or exclusion (`^old`).
+pub(crate) fn push_revision(repo: &Repository, revwalk: &mut Revwalk, rev: &str) -> Result<()> {
+        if let Some(hidden) = rev.strip_prefix('^') {
+                let commit = repo.revparse_single(hidden)
+                        .and_then(|obj| obj.peel_to_commit())
+                        .with_context(|| format!("Failed to resolve revision {:?}", hidden))?;
+                revwalk.hide(commit.id())?;
+        } else if rev.contains("..") {
+                revwalk.push_range(rev)
+                        .with_context(|| format!("Failed to resolve range {:?}", rev))?;
+        } else {
+                let commit = repo.revparse_single(rev)
+                        .and_then(|obj| obj.peel_to_commit())
+                        .with_context(|| format!("Failed to resolve revision {:?}", rev))?;
+                revwalk.push(commit.id())?;
+        }
+        Ok(())
+}
+
+pub fn scan(config: &ScanConfig) -> Result<ContributionReport> {
+        let matcher = EmailMatcher::new(&config.emails, config.partial)
+                .with_orgs(&config.organizations, &config.orgs);
+        let mut report = ContributionReport::new(&config.trailer_kinds);
+        if config.merges == MergeMode::Separate {
+                report.merged = Some(0);
+        }
+
+        let mut buckets = BTreeMap::new();
+        let names = repository_names(&config.paths)?;
+        for (path, name) in config.paths.iter().zip(&names) {
+                scan_repository(config, path, name, &matcher, &mut report, &mut buckets)?;
+        }
+
+        if !config.mail_archives.is_empty() || !config.public_inboxes.is_empty() {
+                report.mailing_list = Some(scan_mail(config, &matcher)?);
+        }
+
+        if let Some(bucket) = config.bucket {
+                report.timeline = fill_gaps(bucket, buckets, |label| report.new_row(label, None));
+        }
+
+        report.organizations.sort_by(|a, b| b.total().cmp(&a.total()).then_with(|| a.name.cmp(&b.name)));
+        report.people.sort_by(|a, b| b.total().cmp(&a.total()).then_with(|| a.name.cmp(&b.name)));
+        report.subsystems.sort_by(|a, b| b.total().cmp(&a.total()).then_with(|| a.name.cmp(&b.name)));
+        for person in &mut report.people {
+                person.subsystems.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        }
+        Ok(report)
+}
+
+/// Reads every mail archive. Addresses go through the first repository's
+/// mailmap, as the archives belong to no repository in particular.
+fn scan_mail(config: &ScanConfig, matcher: &EmailMatcher) -> Result<ListActivity> {
+        let resolver = match config.paths.first() {
+                Some(path) if config.use_mailmap => {
+                        let repo = Repository::open(path)
+                                .with_context(|| format!("Failed to open git repository at {:?}", path))?;
+                        IdentityResolver::new(&repo, config.mailmap_file.as_deref())?
+                }
+                _ => IdentityResolver::disabled(),
+        };
+        let matcher = matcher.resolved(&resolver);
+        let mut scanner = MailScanner::new(config, &matcher, &resolver);
+        for archive in &config.mail_archives {
+                scanner.read_archive(archive)?;
+        }
+        for inbox in &config.public_inboxes {
+                scanner.read_public_inbox(inbox)?;
+        }
+        let mut activity = scanner.activity;
+        activity.replies.sort_by_key(|reply| reply.date);
+        Ok(activity)
+}
+
+/// Whether `msg` has a `Signed-off-by` trailer naming `identity`.
+fn signed_off_by(msg: &str, identity: &Identity, resolver: &IdentityResolver) -> Result<bool> {
+        Ok(parse_trailers(msg)?.iter()
+                .filter(|trailer| trailer.is("Signed-off-by"))
+                .filter_map(|trailer| trailer.identity())
+                .any(|signer| resolver.resolve(signer).email.eq_ignore_ascii_case(&identity.email)))
+}
+
+/// Walks one repository, adding to `report` and to its row `name`.
+fn scan_repository(config: &ScanConfig, path: &Path, name: &str, matcher: &EmailMatcher,
+        report: &mut ContributionReport, buckets: &mut BTreeMap<NaiveDate, ActivityRow>) -> Result<()> {
+        let repo = Repository::open(path)
+                .with_context(|| format!("Failed to open git repository at {:?}", path))?;
+
+        let mut revwalk = repo.revwalk().context("Failed to initialize revision walker")?;
+        push_revisions(&repo, &mut revwalk, config)
+                .with_context(|| format!("In repository {:?}", path))?;
+        revwalk.set_sorting(Sort::TIME)?;
+        if config.first_parent {
+                revwalk.simplify_first_parent()?;
+        }
+
+        let resolver = if config.use_mailmap {
+                IdentityResolver::new(&repo, config.mailmap_file.as_deref())?
+        } else {
+                IdentityResolver::disabled()
+        };
+        // Targets go through the same mailmap as the identities they are
+        // compared with.
+        let matcher = &matcher.resolved(&resolver);
+        let by_org = !config.organizations.is_empty();
+        let org_of = |email: &str| config.organizations.lookup(email).unwrap_or(UNAFFILIATED).to_string();
+        report.repository_entry(name);
+
+        let mut downstream = Vec::new();
+        let mut old_streak = 0;
+        for oid in revwalk {
+                let oid = oid.context("Failed to get object ID")?;
+                let commit = repo.find_commit(oid).context("Failed to find commit")?;
+
+                // The walk is ordered by committer date, so pruning is on
+                // that field, widened by AUTHOR_DATE_SKEW for author dates.
+                if let Some(since) = config.since {
+                        let cutoff = match config.date_field {
+                                DateField::Committer => since,
+                                DateField::Author => since - AUTHOR_DATE_SKEW,
+                        };
+                        if to_datetime(commit.time()) < cutoff {
+                                old_streak += 1;
+                                if old_streak > SINCE_SLOP {
+                                        break;
+                                }
+                        } else {
+                                old_streak = 0;
+                        }
+                }
+
+                let commit_time = match config.date_field {
+                        DateField::Committer => to_datetime(commit.time()),
+                        DateField::Author => to_datetime(commit.author().when()),
+                };
+                if config.since.is_some_and(|since| commit_time < since)
+                        || config.until.is_some_and(|until| commit_time >= until) {
+                        continue;
+                }
+
+                let is_merge = commit.parent_count() > 1;
+                if is_merge && config.merges == MergeMode::Exclude {
+                        continue;
+                }
+                let as_merge = is_merge && config.merges == MergeMode::Separate;
+
+                if !config.path_filters.is_empty() && !touches_paths(&repo, &commit, &config.path_filters)? {
+                        continue;
+                }
+
+                report.total_scanned += 1;
+
+                let mut roles = Vec::new();
+                let mut matched_trailers = Vec::new();
+                // This commit's share of the targets' activity, and who took part.
+                let mut delta = report.new_row("", None);
+                let mut involved = Vec::new();
+
+                let author = resolver.resolve_signature(&commit.author());
+                let authored = author.as_ref().is_some_and(|a| matcher.matches(&a.email));
+                if let Some(author) = &author
+                        && authored {
+                        if as_merge {
+                                *report.merged.get_or_insert(0) += 1;
+                                *report.person_entry(author).merged.get_or_insert(0) += 1;
+                                *delta.merged.get_or_insert(0) += 1;
+                                roles.push(Role::Merger);
+                        } else {
+                                report.authored += 1;
+                                report.person_entry(author).authored += 1;
+                                delta.authored += 1;
+                                roles.push(Role::Author);
+                        }
+                        involved.push(author.clone());
+                }
+                if authored && !is_merge && !config.upstream_ranges.is_empty() {
+                        downstream.push(oid);
+                }
+                if by_org && let Some(author) = &author {
+                        let org = report.org_entry(&org_of(&author.email));
+                        if as_merge {
+                                *org.merged.get_or_insert(0) += 1;
+                        } else {
+                                org.authored += 1;
+                        }
+                }
+
+                // Applying someone else's patch; a target who is both author
+                // and committer only counts as the author.
+                let mut committer = resolver.resolve_signature(&commit.committer())
+                        .filter(|c| author.as_ref().is_none_or(|a| !a.email.eq_ignore_ascii_case(&c.email)));
+                if config.require_signoff
+                        && let Some(c) = &committer
+                        && !signed_off_by(commit.message().unwrap_or_default(), c, &resolver)? {
+                        committer = None;
+                }
+                if let Some(committer) = &committer
+                        && matcher.matches(&committer.email) {
+                        report.committed += 1;
+                        report.person_entry(committer).committed += 1;
+                        delta.committed += 1;
+                        roles.push(Role::Committer);
+                        if !involved.contains(committer) {
+                                involved.push(committer.clone());
+                        }
+                }
+                if by_org && let Some(committer) = &committer {
+                        report.org_entry(&org_of(&committer.email)).committed += 1;
+                }
+
+                if let Some(msg) = commit.message() {
+                        let matches = analyze_trailers(msg, &config.trailer_kinds, &resolver, author.as_ref())?;
+                        report.unparsed_trailers.extend(matches.unparsed.into_iter()
+                                .map(|trailer| UnparsedTrailer { commit: oid, trailer }));
+                        for credit in matches.credited {
+                                if by_org {
+                                        report.org_entry(&org_of(&credit.identity.email)).trailers[credit.kind] += 1;
+                                }
+                                if !matcher.matches(&credit.identity.email) {
+                                        continue;
+                                }
+                                report.person_entry(&credit.identity).trailers[credit.kind] += 1;
+                                delta.trailers[credit.kind] += 1;
+                                if !involved.contains(&credit.identity) {
+                                        involved.push(credit.identity);
+                                }
+                                matched_trailers.push(credit.trailer);
+                                let entry = &mut report.trailers[credit.kind];
+                                entry.count += 1;
+                                let role = Role::Trailer(entry.kind.key.clone());
+                                if !roles.contains(&role) {
+                                        roles.push(role);
+                                }
+                        }
+                }
+
+                if !roles.is_empty() {
+                        // A merge's first-parent diff is the whole merged
+                        // branch, none of it the merger's own lines.
+                        let diffstat = if config.diffstat && !is_merge {
+                                Some(commit_diffstat(&repo, &commit, &config.exclude_paths)?)
+                        } else {
+                                None
+                        };
+                        if let Some(stat) = diffstat
+                                && let Some(author) = &author
+                                && authored {
+                                report.person_entry(author).diffstat += stat;
+                                delta.diffstat += stat;
+                        }
+                        let subsystems = match &config.maintainers {
+                                Some(maintainers) => {
+                                        let paths = changed_paths(&repo, &commit)?;
+                                        maintainers.attribute(paths.iter().map(String::as_str))
+                                }
+                                None => Vec::new(),
+                        };
+                        for name in &subsystems {
+                                report.subsystem_entry(name).add(&delta);
+                                for identity in &involved {
+                                        report.person_entry(identity).count_subsystem(name);
+                                }
+                        }
+                        report.commits.push(MatchedCommit {
+                                id: oid,
+                                repository: name.to_string(),
+                                date: commit_time,
+                                author_email: author.as_ref().map(|a| a.email.clone()).unwrap_or_default(),
+                                author: author.and_then(|a| a.name).unwrap_or_default(),
+                                summary: commit.summary().unwrap_or("No message").to_string(),
+                                roles,
+                                trailers: matched_trailers,
+                                diffstat,
+                                subsystems,
+                        });
+                }
+
+                // Every scanned commit opens its bucket, so quiet periods
+                // inside the scanned history still show up as zeros.
+                if let Some(bucket) = config.bucket {
+                        let start = bucket.start(commit_time.date_naive());
+                        buckets.entry(start)
+                                .or_insert_with(|| report.new_row(&bucket.label(start), None))
+                                .add(&delta);
+                }
+                report.repository_entry(name).add(&delta);
+        }
+
+        if !config.upstream_ranges.is_empty() {
+                let patches = track_upstream(&repo, &config.upstream_ranges, name, &downstream, &resolver)
+                        .with_context(|| format!("In repository {:?}", path))?;
+                report.upstream.get_or_insert_with(Vec::new).extend(patches);
+        }
+
+        Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+        use super::*;
+        use git2::{Oid, Signature};
+
+        /// A throwaway repository that is removed again on drop.
+        struct TempRepo {
+                path: PathBuf,
+                repo: Repository,
+        }
+
+        impl TempRepo {
+                fn new(name: &str) -> Self {
+                        let path = std::env::temp_dir()
+                                .join(format!("git_stats-{}-{}", name, std::process::id()));
+                        let _ = std::fs::remove_dir_all(&path);
+                        let repo = Repository::init(&path).unwrap();
+                        TempRepo { path, repo }
+                }
+
+                /// Commits an empty tree on top of `HEAD`.
+                fn commit(&self, authored: &str, committed: &str) -> Oid {
+                        let author = Signature::new("Bob", "bob@example.org", &time(authored)).unwrap();
+                        let committer = Signature::new("Bob", "bob@example.org", &time(committed)).unwrap();
+                        let tree_id = self.repo.treebuilder(None).unwrap().write().unwrap();
+                        let tree = self.repo.find_tree(tree_id).unwrap();
+                        let parent = self.repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+                        let parents: Vec<_> = parent.iter().collect();
+                        self.repo.commit(Some("HEAD"), &author, &committer, "change", &tree, &parents).unwrap()
+                }
+
+                fn authored_since(&self, since: &str, date_field: DateField) -> usize {
+                        let mut config = ScanConfig::new(&self.path);
+                        config.emails = vec!["bob@example.org".to_string()];
+                        config.since = Some(parse_date(since).unwrap());
+                        config.date_field = date_field;
+                        scan(&config).unwrap().authored
+                }
+        }
+
+        impl Drop for TempRepo {
+                fn drop(&mut self) {
+                        let _ = std::fs::remove_dir_all(&self.path);
+                }
+        }
+
+        fn time(date: &str) -> Time {
+                Time::new(parse_date(date).unwrap().timestamp(), 0)
+        }
+
+        #[test]
+        fn out_of_order_committer_dates_do_not_end_the_walk() {
+                let repo = TempRepo::new("out-of-order");
+                repo.commit("2024-01-05", "2024-01-05");
+                for _ in 0..SINCE_SLOP - 1 {
+                        repo.commit("2023-06-01", "2023-06-01");
+                }
+                repo.commit("2024-01-20", "2024-01-20");
+
+                assert_eq!(repo.authored_since("2024-01-01", DateField::Committer), 2);
+        }
+
+        #[test]
+        fn author_dates_ahead_of_committer_dates_are_found_within_skew() {
+                let repo = TempRepo::new("author-skew");
+                repo.commit("2024-01-03", "2023-12-30");
+                repo.commit("2024-01-20", "2024-01-20");
+
+                assert_eq!(repo.authored_since("2024-01-01", DateField::Committer), 1);
+                assert_eq!(repo.authored_since("2024-01-01", DateField::Author), 2);
+        }
+
+        #[test]
+        fn author_date_scans_still_prune_old_history() {
+                let repo = TempRepo::new("author-prune");
+                // Authored in range but committed far outside the skew window,
+                // behind more old commits than SINCE_SLOP tolerates.
+                repo.commit("2024-01-10", "2022-01-01");
+                for _ in 0..SINCE_SLOP + 1 {
+                        repo.commit("2023-01-01", "2023-01-01");
+                }
+                repo.commit("2024-01-20", "2024-01-20");
+
+                assert_eq!(repo.authored_since("2024-01-01", DateField::Author), 1);
+        }
+}
@@ -0,0 +1,129 @@
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use std::collections::BTreeMap;
+
+use crate::report::ActivityRow;
+
+/// Calendar interval the time series groups commits into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bucket {
+        /// ISO weeks, starting on Monday.
+        Week,
+        Month,
+        Quarter,
+        Year,
+}
+
+impl Bucket {
+        /// First day of the bucket containing `date`.
+        pub fn start(self, date: NaiveDate) -> NaiveDate {
+                match self {
+                        Bucket::Week => date - Duration::days(date.weekday().num_days_from_monday().into()),
+                        Bucket::Month => date.with_day(1).unwrap(),
+                        Bucket::Quarter => NaiveDate::from_ymd_opt(date.year(), date.month0() / 3 * 3 + 1, 1).unwrap(),
+                        Bucket::Year => NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap(),
+                }
+        }
+
+        /// First day of the bucket after the one starting on `start`.
+        pub fn next(self, start: NaiveDate) -> NaiveDate {
+                match self {
+                        Bucket::Week => start + Duration::days(7),
+                        Bucket::Month => start + Months::new(1),
+                        Bucket::Quarter => start + Months::new(3),
+                        Bucket::Year => start + Months::new(12),
+                }
+        }
+
+        /// Sortable name of the bucket starting on `start`, e.g. `2024-W07`,
+        /// `2024-02`, `2024-Q1` or `2024`.
+        pub fn label(self, start: NaiveDate) -> String {
+                match self {
+                        Bucket::Week => start.format("%G-W%V").to_string(),
+                        Bucket::Month => start.format("%Y-%m").to_string(),
+                        Bucket::Quarter => format!("{}-Q{}", start.year(), start.month0() / 3 + 1),
+                        Bucket::Year => start.format("%Y").to_string(),
+                }
+        }
+}
+
+/// Turns rows keyed by bucket start into a contiguous series, inserting
+/// `empty` rows for buckets without any scanned commit so gaps show up as
+/// zeros rather than being skipped.
+pub(crate) fn fill_gaps(bucket: Bucket, rows: BTreeMap<NaiveDate, ActivityRow>,
+        empty: impl Fn(&str) -> ActivityRow) -> Vec<ActivityRow> {
+        let (Some(&first), Some(&last)) = (rows.keys().next(), rows.keys().next_back()) else {
+                return Vec::new();
+        };
+        let mut rows = rows;
+        let mut series = Vec::new();
+        let mut start = first;
+        while start <= last {
+                let label = bucket.label(start);
+                series.push(rows.remove(&start).unwrap_or_else(|| empty(&label)));
+                start = bucket.next(start);
+        }
+        series
+}
+
+#[cfg(test)]
+mod tests {
+        use super::*;
+
+        fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+                NaiveDate::from_ymd_opt(y, m, d).unwrap()
+        }
+
+        fn row(name: &str, authored: usize) -> ActivityRow {
+                let mut row = ActivityRow::new(name, None, 0);
+                row.authored = authored;
+                row
+        }
+
+        #[test]
+        fn iso_weeks_cross_year_boundaries() {
+                // 2024-12-30 is the Monday of ISO week 1 of 2025.
+                let start = Bucket::Week.start(date(2025, 1, 1));
+                assert_eq!(start, date(2024, 12, 30));
+                assert_eq!(Bucket::Week.label(start), "2025-W01");
+                // 2021-01-03 is a Sunday still in ISO week 53 of 2020.
+                let start = Bucket::Week.start(date(2021, 1, 3));
+                assert_eq!(start, date(2020, 12, 28));
+                assert_eq!(Bucket::Week.label(start), "2020-W53");
+                assert_eq!(Bucket::Week.next(start), date(2021, 1, 4));
+        }
+
+        #[test]
+        fn quarters_start_on_their_first_month() {
+                assert_eq!(Bucket::Quarter.start(date(2024, 3, 31)), date(2024, 1, 1));
+                assert_eq!(Bucket::Quarter.start(date(2024, 8, 15)), date(2024, 7, 1));
+                assert_eq!(Bucket::Quarter.label(date(2024, 10, 1)), "2024-Q4");
+                assert_eq!(Bucket::Quarter.next(date(2024, 10, 1)), date(2025, 1, 1));
+        }
+
+        #[test]
+        fn months_and_years() {
+                assert_eq!(Bucket::Month.start(date(2024, 2, 29)), date(2024, 2, 1));
+                assert_eq!(Bucket::Month.label(date(2024, 2, 1)), "2024-02");
+                assert_eq!(Bucket::Month.next(date(2024, 12, 1)), date(2025, 1, 1));
+                assert_eq!(Bucket::Year.start(date(2024, 6, 1)), date(2024, 1, 1));
+                assert_eq!(Bucket::Year.label(date(2024, 1, 1)), "2024");
+        }
+
+        #[test]
+        fn fill_gaps_inserts_empty_buckets() {
+                let rows = BTreeMap::from([
+                        (date(2024, 1, 1), row("2024-01", 2)),
+                        (date(2024, 4, 1), row("2024-04", 1)),
+                ]);
+                let series = fill_gaps(Bucket::Month, rows, |label| row(label, 0));
+                let names: Vec<&str> = series.iter().map(|r| r.name.as_str()).collect();
+                assert_eq!(names, ["2024-01", "2024-02", "2024-03", "2024-04"]);
+                let authored: Vec<usize> = series.iter().map(|r| r.authored).collect();
+                assert_eq!(authored, [2, 0, 0, 1]);
+        }
+
+        #[test]
+        fn fill_gaps_of_nothing_is_empty() {
+                assert!(fill_gaps(Bucket::Week, BTreeMap::new(), |label| row(label, 0)).is_empty());
+        }
+}
@@ -0,0 +1,217 @@
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::Path;
+
+use crate::identity::{Identity, IdentityResolver};
+
+/// A `Key: value` line from the trailer block at the end of a commit message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trailer {
+        pub key: String,
+        /// Value with folded continuation lines joined by single spaces.
+        pub value: String,
+}
+
+impl Trailer {
+        pub fn is(&self, key: &str) -> bool {
+                self.key.eq_ignore_ascii_case(key)
+        }
+
+        pub fn identity(&self) -> Option<Identity> {
+                Identity::parse(&self.value)
+        }
+}
+
+/// Parses only the trailer block of `msg`, following git's rules for what
+/// counts as one (last paragraph, `Key : value` spacing, folded lines).
+pub fn parse_trailers(msg: &str) -> Result<Vec<Trailer>> {
+        let trailers = git2::message_trailers_strs(msg).context("Failed to parse commit trailers")?;
+        Ok(trailers.iter()
+                .map(|(key, value)| Trailer {
+                        key: key.to_string(),
+                        value: value.split_whitespace().collect::<Vec<_>>().join(" "),
+                })
+                .collect())
+}
+
+/// How a trailer kind is attributed to the targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrailerMatch {
+        /// The value names the identity, e.g. `Reviewed-by: Name <email>`.
+        Identity,
+        /// Like `Identity`, but ignored when it names the commit author, so
+        /// `Signed-off-by` only credits maintainers and committers.
+        NotAuthor,
+        /// The value is not an identity (`Fixes`, `Link`); the trailer is
+        /// counted on commits authored by a target.
+        Authored,
+}
+
+impl TrailerMatch {
+        fn parse(s: &str) -> Result<Self> {
+                match s {
+                        "identity" => Ok(TrailerMatch::Identity),
+                        "not-author" => Ok(TrailerMatch::NotAuthor),
+                        "authored" => Ok(TrailerMatch::Authored),
+                        _ => bail!("Unknown trailer match mode {:?} (expected identity, not-author or authored)", s),
+                }
+        }
+}
+
+/// One row of the trailer taxonomy: which key to count and how to label it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrailerKind {
+        pub key: String,
+        pub label: String,
+        /// What a credited identity is called in exports, e.g. `reviewer`.
+        pub role: String,
+        pub mode: TrailerMatch,
+}
+
+impl TrailerKind {
+        /// Builds a kind for `key` with the label, role and mode git users
+        /// expect, e.g. `Reviewed-by` is labelled "Reviewed" and credits a
+        /// "reviewer", and `Fixes` is counted on authored commits.
+        pub fn new(key: &str) -> Self {
+                let label = key.strip_suffix("-by").unwrap_or(key);
+                let lower = key.to_ascii_lowercase();
+                let mode = match lower.as_str() {
+                        "signed-off-by" => TrailerMatch::NotAuthor,
+                        "fixes" | "link" => TrailerMatch::Authored,
+                        _ => TrailerMatch::Identity,
+                };
+                let role = match lower.as_str() {
+                        "signed-off-by" => "signer".to_string(),
+                        _ => match lower.strip_suffix("ed-by") {
+                                Some(stem) => format!("{}er", stem),
+                                None => lower.clone(),
+                        },
+                };
+                TrailerKind { key: key.to_string(), label: label.to_string(), role, mode }
+        }
+
+        /// The four kinds counted out of the box.
+        pub fn defaults() -> Vec<TrailerKind> {
+                ["Reviewed-by", "Acked-by", "Tested-by", "Reported-by"]
+                        .iter()
+                        .map(|key| TrailerKind::new(key))
+                        .collect()
+        }
+
+        /// Parses a `Key[:Label[:mode]]` spec such as `Suggested-by`,
+        /// `Cc:Copied` or `Signed-off-by:Maintained:not-author`.
+        pub fn parse_spec(spec: &str) -> Result<Self> {
+                let mut parts = spec.split(':').map(str::trim);
+                let key = parts.next().unwrap_or_default();
+                if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+                        bail!("Invalid trailer key in {:?}", spec);
+                }
+                let mut kind = TrailerKind::new(key);
+                if let Some(label) = parts.next().filter(|l| !l.is_empty()) {
+                        kind.label = label.to_string();
+                }
+                if let Some(mode) = parts.next() {
+                        kind.mode = TrailerMatch::parse(mode)?;
+                }
+                if parts.next().is_some() {
+                        bail!("Too many fields in trailer spec {:?}", spec);
+                }
+                Ok(kind)
+        }
+
+        /// Reads one spec per line; blank lines and `#` comments are skipped.
+        pub fn load_file(path: &Path) -> Result<Vec<TrailerKind>> {
+                let content = fs::read_to_string(path)
+                        .with_context(|| format!("Failed to read trailer file {:?}", path))?;
+                content.lines()
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                        .map(|line| TrailerKind::parse_spec(line)
+                                .with_context(|| format!("In trailer file {:?}", path)))
+                        .collect()
+        }
+}
+
+/// A counted trailer and the mailmapped identity it gives credit to.
+#[derive(Debug)]
+pub(crate) struct Credit {
+        /// Index into the kind table.
+        pub kind: usize,
+        pub identity: Identity,
+        pub trailer: Trailer,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct TrailerMatches {
+        pub credited: Vec<Credit>,
+        /// Identity trailers whose value has no parseable email.
+        pub unparsed: Vec<Trailer>,
+}
+
+pub(crate) fn analyze_trailers(msg: &str, kinds: &[TrailerKind], resolver: &IdentityResolver,
+        author: Option<&Identity>) -> Result<TrailerMatches> {
+        let mut matches = TrailerMatches::default();
+        for trailer in parse_trailers(msg)? {
+                let Some(index) = kinds.iter().position(|kind| trailer.is(&kind.key)) else { continue };
+                let mode = kinds[index].mode;
+                if mode == TrailerMatch::Authored {
+                        if let Some(author) = author {
+                                matches.credited.push(Credit { kind: index, identity: author.clone(), trailer });
+                        }
+                        continue;
+                }
+                match trailer.identity().map(|identity| resolver.resolve(identity)) {
+                        Some(identity) if mode == TrailerMatch::NotAuthor
+                                && author.is_some_and(|a| a.email.eq_ignore_ascii_case(&identity.email)) => {}
+                        Some(identity) => matches.credited.push(Credit { kind: index, identity, trailer }),
+                        None => matches.unparsed.push(trailer),
+                }
+        }
+        Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+        use super::*;
+
+        #[test]
+        fn parses_only_the_trailer_block() {
+                let msg = "Subject\n\nReviewed-by: not a trailer, body text\nmore body\n\nAcked-by: A <a@x.org>\nCc: c@x.org\n";
+                let trailers = parse_trailers(msg).unwrap();
+                let keys: Vec<&str> = trailers.iter().map(|t| t.key.as_str()).collect();
+                assert_eq!(keys, ["Acked-by", "Cc"]);
+        }
+
+        #[test]
+        fn joins_folded_lines_and_spaced_separators() {
+                let msg = "Subject\n\nBody\n\nReported-by : Jane\n  Doe <jane@x.org>\n";
+                let trailers = parse_trailers(msg).unwrap();
+                assert_eq!(trailers, [Trailer { key: "Reported-by".to_string(), value: "Jane Doe <jane@x.org>".to_string() }]);
+        }
+
+        #[test]
+        fn trailer_without_email_has_no_identity() {
+                let msg = "Subject\n\nBody\n\nReported-by: syzbot\n";
+                let trailers = parse_trailers(msg).unwrap();
+                assert!(trailers[0].is("reported-by"));
+                assert_eq!(trailers[0].identity(), None);
+        }
+
+        #[test]
+        fn parses_trailer_specs() {
+                let kind = TrailerKind::parse_spec("Signed-off-by:Maintained:not-author").unwrap();
+                assert_eq!((kind.key.as_str(), kind.label.as_str(), kind.mode), ("Signed-off-by", "Maintained", TrailerMatch::NotAuthor));
+                let kind = TrailerKind::parse_spec("Suggested-by").unwrap();
+                assert_eq!((kind.label.as_str(), kind.role.as_str(), kind.mode), ("Suggested", "suggester", TrailerMatch::Identity));
+                assert_eq!(TrailerKind::new("Reviewed-by").role, "reviewer");
+                let err = TrailerKind::parse_spec("Fixes::").unwrap_err();
+                assert!(err.to_string().contains("Unknown trailer match mode"));
+        }
+
+        #[test]
+        fn rejects_bad_trailer_specs() {
+                assert!(TrailerKind::parse_spec("").is_err());
+                assert!(TrailerKind::parse_spec("Bad key").is_err());
+                assert!(TrailerKind::parse_spec("Cc:Copied:identity:extra").is_err());
+        }
+}
@@ -0,0 +1,164 @@
+use anyhow::{Context, Result};
+use chrono::DateTime;
+use git2::{Oid, Repository, Sort};
+use std::collections::{HashMap, HashSet};
+
+use crate::diffstat::patch_id;
+use crate::identity::IdentityResolver;
+use crate::report::{Landing, UpstreamMatch, UpstreamPatch};
+use crate::scan::push_revision;
+
+/// Markers vendor trees put in front of picked patches' subjects, as
+/// `FROMLIST: ` or `[BACKPORT] `.
+const VENDOR_MARKERS: [&str; 6] = ["FROMLIST", "FROMGIT", "UPSTREAM", "BACKPORT", "ANDROID", "CHROMIUM"];
+
+/// Subjects (after the last `: `) too common to identify a patch.
+const GENERIC_SUBJECTS: [&str; 8] = ["fix typo", "fix typos", "fix build", "fix warning", "fix warnings",
+        "cleanup", "clean up", "update"];
+
+/// Looks for each of the `downstream` commits in the upstream `ranges` of
+/// the same repository, first by patch-id and then by subject, and names
+/// the upstream release it landed in. A subject only matches when exactly
+/// one upstream commit has it and it is specific enough.
+///
+/// Every non-merge upstream commit is diffed once for its patch-id, so a
+/// long upstream range takes a while.
+pub(crate) fn track_upstream(repo: &Repository, ranges: &[String], name: &str, downstream: &[Oid],
+        resolver: &IdentityResolver) -> Result<Vec<UpstreamPatch>> {
+        if downstream.is_empty() {
+                return Ok(Vec::new());
+        }
+        let mut revwalk = repo.revwalk().context("Failed to initialize revision walker")?;
+        for range in ranges {
+                push_revision(repo, &mut revwalk, range)?;
+        }
+        // Oldest first, so a change merged twice maps to its first landing.
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+
+        let mut upstream = HashSet::new();
+        let mut by_patch_id = HashMap::new();
+        let mut by_subject = HashMap::new();
+        for oid in revwalk {
+                let oid = oid.context("Failed to get object ID")?;
+                upstream.insert(oid);
+                let commit = repo.find_commit(oid).context("Failed to find commit")?;
+                if commit.parent_count() > 1 {
+                        continue;
+                }
+                if let Some(id) = patch_id(repo, &commit)? {
+                        by_patch_id.entry(id).or_insert(oid);
+                }
+                let key = subject_key(commit.summary().unwrap_or_default());
+                if !is_generic(&key) {
+                        // A second commit with the subject makes it ambiguous.
+                        by_subject.entry(key).and_modify(|id| *id = None).or_insert(Some(oid));
+                }
+        }
+        let releases = releases(repo, &upstream)?;
+
+        let mut patches = Vec::new();
+        for &oid in downstream {
+                let commit = repo.find_commit(oid).context("Failed to find commit")?;
+                let summary = commit.summary().unwrap_or("No message").to_string();
+                let found = match patch_id(repo, &commit)?.and_then(|id| by_patch_id.get(&id)) {
+                        Some(&id) => Some((id, UpstreamMatch::PatchId)),
+                        None => by_subject.get(&subject_key(&summary)).copied().flatten()
+                                .map(|id| (id, UpstreamMatch::Subject)),
+                };
+                let landed = match found {
+                        Some((id, matched_by)) => Some(Landing {
+                                id,
+                                matched_by,
+                                release: release_of(repo, &releases, id)?,
+                        }),
+                        None => None,
+                };
+                patches.push(UpstreamPatch {
+                        id: oid,
+                        repository: name.to_string(),
+                        date: DateTime::from_timestamp(commit.author().when().seconds(), 0).unwrap_or_default(),
+                        author_email: resolver.resolve_signature(&commit.author()).map(|a| a.email).unwrap_or_default(),
+                        summary,
+                        landed,
+                });
+        }
+        Ok(patches)
+}
+
+/// Tags pointing into the upstream history, oldest first.
+fn releases(repo: &Repository, upstream: &HashSet<Oid>) -> Result<Vec<(String, Oid)>> {
+        let mut releases = Vec::new();
+        for reference in repo.references_glob("refs/tags/*").context("Failed to list tags")? {
+                let reference = reference?;
+                let Ok(commit) = reference.peel_to_commit() else {
+                        continue;
+                };
+                if upstream.contains(&commit.id())
+                        && let Some(name) = reference.shorthand() {
+                        releases.push((commit.time().seconds(), name.to_string(), commit.id()));
+                }
+        }
+        releases.sort();
+        Ok(releases.into_iter().map(|(_, name, id)| (name, id)).collect())
+}
+
+/// The first of `releases` that contains `id`.
+fn release_of(repo: &Repository, releases: &[(String, Oid)], id: Oid) -> Result<Option<String>> {
+        for (name, tag) in releases {
+                if *tag == id || repo.graph_descendant_of(*tag, id)? {
+                        return Ok(Some(name.clone()));
+                }
+        }
+        Ok(None)
+}
+
+/// A subject without the vendor markers in front of it, with whitespace
+/// collapsed. Subsystem prefixes such as `PCI: ` are kept.
+fn subject_key(subject: &str) -> String {
+        let mut rest = subject.trim();
+        loop {
+                let (marker, tail) = match rest.strip_prefix('[') {
+                        Some(bracketed) => match bracketed.split_once(']') {
+                                Some((inside, tail)) => (inside.split_whitespace().next().unwrap_or_default(), tail),
+                                None => break,
+                        },
+                        None => match rest.split_once(':') {
+                                Some((marker, tail)) => (marker.trim(), tail),
+                                None => break,
+                        },
+                };
+                if !VENDOR_MARKERS.iter().any(|m| m.eq_ignore_ascii_case(marker)) {
+                        break;
+                }
+                rest = tail.trim_start();
+        }
+        rest.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Whether `key` says too little to match on: under three words, or a
+/// stock phrase like "fix typo" after the subsystem prefix.
+fn is_generic(key: &str) -> bool {
+        let summary = key.rsplit(": ").next().unwrap_or(key).to_lowercase();
+        key.split_whitespace().count() < 3 || GENERIC_SUBJECTS.contains(&summary.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+        use super::*;
+
+        #[test]
+        fn subject_key_strips_vendor_markers_only() {
+                assert_eq!(subject_key("FROMLIST: PCI: fix leak"), "PCI: fix leak");
+                assert_eq!(subject_key("[BACKPORT 5.10] UPSTREAM: usb:  fix  leak"), "usb: fix leak");
+                assert_eq!(subject_key("ANDROID: [PATCH] x"), "[PATCH] x");
+                assert_ne!(subject_key("PCI: fix leak"), subject_key("USB: fix leak"));
+                assert_eq!(subject_key("ARM: dts: add board"), "ARM: dts: add board");
+        }
+
+        #[test]
+        fn generic_subjects() {
+                assert!(is_generic("fix build"));
+                assert!(is_generic("net: fix typos"));
+                assert!(!is_generic("net: fix leak in probe"));
+        }
+}